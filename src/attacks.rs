@@ -0,0 +1,222 @@
+use crate::magic;
+use crate::{Bitboard, Color, Square};
+
+// (rank, file) offsets of the step and ray patterns of each piece
+const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+const KING_STEPS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+const WHITE_PAWN_CAPTURES: [(i8, i8); 2] = [(1, -1), (1, 1)];
+const BLACK_PAWN_CAPTURES: [(i8, i8); 2] = [(-1, -1), (-1, 1)];
+
+// Ray directions, the first four increase the square index and the rest decrease it
+const DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (0, 1),
+    (1, 1),
+    (1, -1),
+    (-1, 0),
+    (0, -1),
+    (-1, -1),
+    (-1, 1),
+];
+const ROOK_DIRECTIONS: [usize; 4] = [0, 1, 4, 5];
+const BISHOP_DIRECTIONS: [usize; 4] = [2, 3, 6, 7];
+
+static KNIGHT_ATTACKS: [Bitboard; 64] = step_table(&KNIGHT_JUMPS);
+static KING_ATTACKS: [Bitboard; 64] = step_table(&KING_STEPS);
+static PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    step_table(&WHITE_PAWN_CAPTURES),
+    step_table(&BLACK_PAWN_CAPTURES),
+];
+static RAYS: [[Bitboard; 64]; 8] = [
+    ray_table(DIRECTIONS[0]),
+    ray_table(DIRECTIONS[1]),
+    ray_table(DIRECTIONS[2]),
+    ray_table(DIRECTIONS[3]),
+    ray_table(DIRECTIONS[4]),
+    ray_table(DIRECTIONS[5]),
+    ray_table(DIRECTIONS[6]),
+    ray_table(DIRECTIONS[7]),
+];
+
+const fn on_board(rank: i8, file: i8) -> bool {
+    rank >= 0 && rank < 8 && file >= 0 && file < 8
+}
+
+// Squares one offset away from each square
+const fn step_table(offsets: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [Bitboard::EMPTY; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let mut bits = 0u64;
+        let mut i = 0;
+        while i < offsets.len() {
+            let rank = (sq / 8) as i8 + offsets[i].0;
+            let file = (sq % 8) as i8 + offsets[i].1;
+            if on_board(rank, file) {
+                bits |= 1 << (8 * rank + file);
+            }
+            i += 1;
+        }
+        table[sq] = Bitboard(bits);
+        sq += 1;
+    }
+    table
+}
+
+// Squares from each square to the edge of the board in one direction
+const fn ray_table((dr, df): (i8, i8)) -> [Bitboard; 64] {
+    let mut table = [Bitboard::EMPTY; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let mut bits = 0u64;
+        let mut rank = (sq / 8) as i8 + dr;
+        let mut file = (sq % 8) as i8 + df;
+        while on_board(rank, file) {
+            bits |= 1 << (8 * rank + file);
+            rank += dr;
+            file += df;
+        }
+        table[sq] = Bitboard(bits);
+        sq += 1;
+    }
+    table
+}
+
+pub fn knight_attacks(sq: Square) -> Bitboard {
+    KNIGHT_ATTACKS[sq as usize]
+}
+
+pub fn king_attacks(sq: Square) -> Bitboard {
+    KING_ATTACKS[sq as usize]
+}
+
+// Squares attacked by a pawn of `color` on `sq`
+pub fn pawn_attacks(sq: Square, color: Color) -> Bitboard {
+    PAWN_ATTACKS[color as usize][sq as usize]
+}
+
+// Squares along a ray up to and including the first occupied square
+fn ray_attacks(dir: usize, sq: Square, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[dir][sq as usize];
+    let blocker = if dir < 4 {
+        (ray & occupied).first()
+    } else {
+        (ray & occupied).last()
+    };
+    match blocker {
+        Some(blocker) => ray ^ RAYS[dir][blocker as usize],
+        None => ray,
+    }
+}
+
+// Rook attacks traced ray by ray, used to fill and check the magic tables
+pub fn rook_ray_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    ROOK_DIRECTIONS.iter().fold(Bitboard::EMPTY, |acc, &dir| {
+        acc | ray_attacks(dir, sq, occupied)
+    })
+}
+
+// Bishop attacks traced ray by ray, used to fill and check the magic tables
+pub fn bishop_ray_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    BISHOP_DIRECTIONS.iter().fold(Bitboard::EMPTY, |acc, &dir| {
+        acc | ray_attacks(dir, sq, occupied)
+    })
+}
+
+// Squares whose occupancy can block a ray, so excluding the last one on each ray
+fn relevant_mask(directions: &[usize], sq: Square) -> Bitboard {
+    directions.iter().fold(Bitboard::EMPTY, |acc, &dir| {
+        let ray = RAYS[dir][sq as usize];
+        let edge = if dir < 4 { ray.last() } else { ray.first() };
+        match edge {
+            Some(edge) => acc | (ray ^ Bitboard::from(edge)),
+            None => acc,
+        }
+    })
+}
+
+pub fn rook_mask(sq: Square) -> Bitboard {
+    relevant_mask(&ROOK_DIRECTIONS, sq)
+}
+
+pub fn bishop_mask(sq: Square) -> Bitboard {
+    relevant_mask(&BISHOP_DIRECTIONS, sq)
+}
+
+pub fn rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    magic::rook_attacks(sq, occupied)
+}
+
+pub fn bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    magic::bishop_attacks(sq, occupied)
+}
+
+pub fn queen_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(sq, occupied) | bishop_attacks(sq, occupied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Square::*;
+
+    fn squares(squares: &[Square]) -> Bitboard {
+        squares.iter().copied().collect()
+    }
+
+    #[test]
+    fn test_step_attacks() {
+        assert_eq!(knight_attacks(A1), squares(&[B3, C2]));
+        assert_eq!(knight_attacks(E4).count(), 8);
+        assert_eq!(king_attacks(H8), squares(&[G8, G7, H7]));
+        assert_eq!(king_attacks(D4).count(), 8);
+        assert_eq!(pawn_attacks(E4, Color::White), squares(&[D5, F5]));
+        assert_eq!(pawn_attacks(A7, Color::Black), squares(&[B6]));
+        assert_eq!(pawn_attacks(H8, Color::White), Bitboard::EMPTY);
+    }
+
+    #[test]
+    fn test_slider_attacks() {
+        let occupied = squares(&[D2, D7, B4, G4, F6, B6]);
+        assert_eq!(
+            rook_attacks(D4, occupied),
+            squares(&[D2, D3, D5, D6, D7, B4, C4, E4, F4, G4])
+        );
+        assert_eq!(
+            bishop_attacks(D4, occupied),
+            squares(&[C3, B2, A1, E3, F2, G1, C5, B6, E5, F6])
+        );
+        assert_eq!(rook_attacks(A1, Bitboard::EMPTY).count(), 14);
+        assert_eq!(bishop_attacks(A1, Bitboard::EMPTY).count(), 7);
+        assert_eq!(queen_attacks(E4, Bitboard::EMPTY).count(), 27);
+    }
+
+    #[test]
+    fn test_relevant_masks() {
+        assert_eq!(rook_mask(A1).count(), 12);
+        assert_eq!(rook_mask(E4).count(), 10);
+        assert_eq!(bishop_mask(A1).count(), 6);
+        assert_eq!(bishop_mask(E4).count(), 9);
+        assert!(!rook_mask(D4).contains(D8));
+        assert!(rook_mask(D4).contains(D7));
+        assert!(!bishop_mask(D4).contains(H8));
+    }
+}
@@ -0,0 +1,148 @@
+// Universal Chess Interface front-end, reading commands from stdin and
+// answering on stdout
+extern crate qchess;
+
+use std::io::{self, BufRead};
+use std::time::Duration;
+
+use qchess::{Board, Color, Engine, SearchInfo, SearchLimits, TranspositionTable};
+
+// Largest transposition table offered to GUIs, in megabytes
+const MAX_HASH_MB: usize = 65536;
+
+fn main() {
+    let mut engine = Engine::new();
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => break,
+        };
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("uci") => {
+                println!("id name qchess {}", env!("CARGO_PKG_VERSION"));
+                println!("id author {}", env!("CARGO_PKG_AUTHORS"));
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    TranspositionTable::DEFAULT_SIZE_MB,
+                    MAX_HASH_MB
+                );
+                println!("option name Clear Hash type button");
+                println!("option name UCI_Chess960 type check default false");
+                println!("option name UnderPromotions type check default true");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => engine.new_game(),
+            Some("position") => position(&mut engine, &mut tokens),
+            Some("go") => {
+                let limits = go_limits(&mut tokens);
+                let board = engine.game().board.clone();
+                let root = board.clone();
+                let on_info = move |info: &SearchInfo| print_info(&board, info);
+                engine.go(limits, on_info, move |best| {
+                    let best = best.map_or_else(|| "0000".to_string(), |mv| root.move_to_uci(mv));
+                    println!("bestmove {}", best);
+                });
+            }
+            Some("stop") => engine.stop(),
+            Some("setoption") => set_option(&mut engine, &mut tokens),
+            Some("quit") => break,
+            // Unknown commands are ignored, as the protocol asks
+            _ => {}
+        }
+    }
+    engine.stop();
+}
+
+// `position [startpos | fen <fen>] [moves <move>...]`
+fn position<'a>(engine: &mut Engine, tokens: &mut impl Iterator<Item = &'a str>) {
+    let board = match tokens.next() {
+        Some("startpos") => {
+            tokens.next();
+            Board::default()
+        }
+        Some("fen") => {
+            let fen: Vec<&str> = tokens.take_while(|&token| token != "moves").collect();
+            match Board::from_fen(&fen.join(" ")) {
+                Ok(board) => board,
+                Err(err) => {
+                    println!("info string {}", err);
+                    return;
+                }
+            }
+        }
+        _ => return,
+    };
+    engine.set_position(board);
+    for text in tokens {
+        if engine.play_move(text).is_none() {
+            println!("info string Illegal move {}", text);
+            return;
+        }
+    }
+}
+
+// `go [depth <d>] [nodes <n>] [movetime <ms>] [wtime <ms>] [btime <ms>]
+// [winc <ms>] [binc <ms>] [movestogo <n>] [infinite]`
+fn go_limits<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> SearchLimits {
+    let mut limits = SearchLimits::default();
+    while let Some(token) = tokens.next() {
+        // Clocks can go negative in some GUIs, which is treated as no time left
+        let mut number = || -> Option<u64> {
+            let value: i64 = tokens.next()?.parse().ok()?;
+            Some(value.max(0) as u64)
+        };
+        let millis = |ms: Option<u64>| ms.map(Duration::from_millis);
+        match token {
+            "depth" => limits.depth = number().map(|depth| depth.min(u8::MAX as u64) as u8),
+            "nodes" => limits.nodes = number(),
+            "movetime" => limits.movetime = millis(number()),
+            "wtime" => limits.time[Color::White as usize] = millis(number()),
+            "btime" => limits.time[Color::Black as usize] = millis(number()),
+            "winc" => {
+                limits.increment[Color::White as usize] = millis(number()).unwrap_or_default()
+            }
+            "binc" => {
+                limits.increment[Color::Black as usize] = millis(number()).unwrap_or_default()
+            }
+            "movestogo" => limits.moves_to_go = number().map(|moves| moves as u32),
+            "infinite" => limits.infinite = true,
+            _ => {}
+        }
+    }
+    limits
+}
+
+// `setoption name <id> [value <x>]`, where both may contain spaces
+fn set_option<'a>(engine: &mut Engine, tokens: &mut impl Iterator<Item = &'a str>) {
+    let mut name = Vec::new();
+    let mut value = Vec::new();
+    let mut in_value = false;
+    for token in tokens.skip_while(|&token| token == "name") {
+        match token {
+            "value" if !in_value => in_value = true,
+            _ if in_value => value.push(token),
+            _ => name.push(token),
+        }
+    }
+    let name = name.join(" ");
+    if !engine.set_option(&name, &value.join(" ")) {
+        println!("info string Unknown option {}", name);
+    }
+}
+
+fn print_info(board: &Board, info: &SearchInfo) {
+    let pv: Vec<String> = info.pv.iter().map(|&mv| board.move_to_uci(mv)).collect();
+    println!(
+        "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        info.depth,
+        info.score,
+        info.nodes,
+        info.nps(),
+        info.hashfull,
+        info.time.as_millis(),
+        pv.join(" ")
+    );
+}
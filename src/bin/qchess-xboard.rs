@@ -0,0 +1,289 @@
+// Chess Engine Communication Protocol (xboard/WinBoard protocol 2) front-end,
+// reading commands from stdin and answering on stdout
+extern crate qchess;
+
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use qchess::{Board, Color, Engine, Move, Score, SearchInfo, SearchLimits};
+
+enum Event {
+    Command(String),
+    // Search result, tagged with the search it came from
+    BestMove(u32, Option<Move>),
+}
+
+struct Xboard {
+    engine: Engine,
+    events: Sender<Event>,
+    // Side the engine plays, or `None` in force mode
+    engine_color: Option<Color>,
+    // Bumped whenever a running search is abandoned, so its move is ignored
+    search_id: u32,
+    // Limits from `level`, `st` and `sd`, with clocks filled in by `time` and `otim`
+    limits: SearchLimits,
+    moves_per_session: u32,
+    // Whether to print thinking output
+    post: bool,
+    // Whether xboard accepted `usermove=1`, so moves never arrive bare
+    usermove: bool,
+}
+
+fn main() {
+    let (events, rx) = mpsc::channel();
+    let commands = events.clone();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => commands.send(Event::Command(line)).unwrap(),
+                Err(_) => break,
+            }
+        }
+        commands.send(Event::Command("quit".to_string())).ok();
+    });
+
+    let mut xboard = Xboard::new(events);
+    for event in rx {
+        match event {
+            Event::Command(line) => {
+                if !xboard.command(&line) {
+                    break;
+                }
+            }
+            Event::BestMove(id, best) => {
+                if id == xboard.search_id {
+                    xboard.engine_move(best);
+                }
+            }
+        }
+    }
+    xboard.cancel_search();
+}
+
+impl Xboard {
+    fn new(events: Sender<Event>) -> Self {
+        Xboard {
+            engine: Engine::new(),
+            events,
+            engine_color: Some(Color::Black),
+            search_id: 0,
+            // xboard's own default of 40 moves in 5 minutes, until told otherwise
+            limits: SearchLimits {
+                time: [Some(Duration::from_secs(5 * 60)); 2],
+                ..SearchLimits::default()
+            },
+            moves_per_session: 40,
+            post: false,
+            usermove: false,
+        }
+    }
+
+    // Handles one command line, returning `false` once told to quit
+    fn command(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        let command = match tokens.next() {
+            Some(command) => command,
+            None => return true,
+        };
+        let args: Vec<&str> = tokens.collect();
+        match command {
+            "xboard" | "random" | "hard" | "easy" | "computer" | "name" | "rating" | "ics" => {}
+            // Draw offers are declined by ignoring them, and there are no hints
+            // or opening book to show
+            "draw" | "hint" | "bk" => {}
+            "accepted" | "rejected" => {
+                if args.first() == Some(&"usermove") {
+                    self.usermove = command == "accepted";
+                }
+            }
+            "protover" => println!(
+                "feature myname=\"qchess {}\" ping=1 setboard=1 usermove=1 \
+                 sigint=0 sigterm=0 colors=0 analyze=0 reuse=1 memory=1 done=1",
+                env!("CARGO_PKG_VERSION")
+            ),
+            "ping" => println!("pong {}", args.first().unwrap_or(&"")),
+            "new" => {
+                self.cancel_search();
+                self.engine.new_game();
+                self.engine_color = Some(Color::Black);
+                self.limits.depth = None;
+            }
+            "force" | "result" => {
+                self.cancel_search();
+                self.engine_color = None;
+            }
+            "go" => {
+                self.cancel_search();
+                self.engine_color = Some(self.engine.game().board.turn);
+                self.start_search();
+            }
+            // Play the side not on move, waiting for the opponent's move
+            "playother" => {
+                self.cancel_search();
+                self.engine_color = Some(!self.engine.game().board.turn);
+            }
+            // Move now, keeping whatever the search has found
+            "?" => self.engine.stop(),
+            "usermove" => self.user_move(args.first().unwrap_or(&"")),
+            "undo" => {
+                self.cancel_search();
+                self.engine.game_mut().undo_move();
+            }
+            "remove" => {
+                self.cancel_search();
+                self.engine.game_mut().undo_move();
+                self.engine.game_mut().undo_move();
+            }
+            "setboard" => {
+                self.cancel_search();
+                match Board::from_fen(&args.join(" ")) {
+                    Ok(board) => self.engine.set_position(board),
+                    Err(_) => println!("tellusererror Illegal position"),
+                }
+            }
+            "level" => self.level(&args),
+            "st" => self.limits.movetime = args.first().and_then(|&st| seconds(st)),
+            "sd" => self.limits.depth = args.first().and_then(|depth| depth.parse().ok()),
+            "time" | "otim" => {
+                let color = match (command, self.engine_color) {
+                    ("time", Some(color)) => color,
+                    ("time", None) => self.engine.game().board.turn,
+                    (_, Some(color)) => !color,
+                    (_, None) => !self.engine.game().board.turn,
+                };
+                let centis = args.first().and_then(|time| time.parse::<i64>().ok());
+                self.limits.time[color as usize] =
+                    centis.map(|centis| Duration::from_millis(centis.max(0) as u64 * 10));
+            }
+            // Megabytes the engine may use, all of which goes to the hash table
+            "memory" => {
+                self.cancel_search();
+                let size_mb = args.first().unwrap_or(&"");
+                if !self.engine.set_option("Hash", size_mb) {
+                    println!("Error (bad memory size): {}", size_mb);
+                }
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "quit" => return false,
+            // Without `usermove=1` moves arrive bare, so accept those too
+            _ if !self.usermove && command.parse::<Move>().is_ok() => self.user_move(command),
+            _ => println!("Error (unknown command): {}", command),
+        }
+        true
+    }
+
+    fn user_move(&mut self, text: &str) {
+        if self.engine.is_searching() {
+            self.cancel_search();
+        }
+        if self.engine.play_move(text).is_none() {
+            println!("Illegal move: {}", text);
+            return;
+        }
+        if !self.report_outcome() && self.engine_color == Some(self.engine.game().board.turn) {
+            self.start_search();
+        }
+    }
+
+    fn engine_move(&mut self, best: Option<Move>) {
+        if let Some(mv) = best {
+            let text = self.engine.game().board.move_to_uci(mv);
+            self.engine.game_mut().make_move(mv);
+            println!("move {}", text);
+        }
+        self.report_outcome();
+    }
+
+    // Prints the result if the game is over, returning whether it was
+    fn report_outcome(&self) -> bool {
+        match self.engine.game().outcome() {
+            Some(outcome) => {
+                println!("{} {{{:?}}}", outcome.score(), outcome.reason);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn start_search(&mut self) {
+        let mut limits = self.limits;
+        if self.moves_per_session > 0 {
+            let moves_played = u32::from(self.engine.game().board.fullmove_count).saturating_sub(1);
+            limits.moves_to_go =
+                Some(self.moves_per_session - moves_played % self.moves_per_session);
+        }
+        let events = self.events.clone();
+        let id = self.search_id;
+        let post = self.post;
+        let board = self.engine.game().board.clone();
+        let on_info = move |info: &SearchInfo| {
+            if post {
+                print_thinking(&board, info);
+            }
+        };
+        self.engine.go(limits, on_info, move |best| {
+            events.send(Event::BestMove(id, best)).ok();
+        });
+    }
+
+    // Stops the running search without playing its move
+    fn cancel_search(&mut self) {
+        self.search_id = self.search_id.wrapping_add(1);
+        self.engine.stop();
+    }
+
+    // `level MPS BASE INC`, with the base time in minutes or `minutes:seconds`
+    // and the increment in seconds
+    fn level(&mut self, args: &[&str]) {
+        if let [mps, base, inc] = *args {
+            self.moves_per_session = mps.parse().unwrap_or(0);
+            let base = match base.split_once(':') {
+                Some((minutes, secs)) => minutes.parse::<u64>().ok().map(|minutes| {
+                    Duration::from_secs(minutes * 60 + secs.parse::<u64>().unwrap_or(0))
+                }),
+                None => base
+                    .parse::<u64>()
+                    .ok()
+                    .map(|minutes| Duration::from_secs(minutes * 60)),
+            };
+            let inc = seconds(inc).unwrap_or_default();
+            self.limits.time = [base; 2];
+            self.limits.increment = [inc; 2];
+            self.limits.movetime = None;
+        }
+    }
+}
+
+// `ply score time nodes pv`, with the time in centiseconds
+fn print_thinking(board: &Board, info: &SearchInfo) {
+    let pv: Vec<String> = info.pv.iter().map(|&mv| board.move_to_uci(mv)).collect();
+    println!(
+        "{} {} {} {} {}",
+        info.depth,
+        cecp_score(info.score),
+        info.time.as_millis() / 10,
+        info.nodes,
+        pv.join(" ")
+    );
+}
+
+// Centipawns, except mates, which are 100000 plus the moves to mate, negated
+// when getting mated
+fn cecp_score(score: Score) -> i32 {
+    match score.mate_moves() {
+        Some(moves) if moves > 0 => 100_000 + moves,
+        Some(moves) => -100_000 + moves,
+        None => score.0,
+    }
+}
+
+fn seconds(text: &str) -> Option<Duration> {
+    text.parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64)
+}
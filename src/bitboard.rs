@@ -0,0 +1,285 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+use crate::{Color, File, Rank, Square};
+
+// Set of squares, with bit `n` representing the square with index `n`
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
+pub struct Bitboard(pub u64);
+
+#[derive(Clone, Debug)]
+pub struct BitboardIter {
+    bits: u64,
+}
+
+impl Bitboard {
+    pub const EMPTY: Self = Self(0);
+    pub const FULL: Self = Self(!0);
+    pub const LIGHT_SQUARES: Self = Self(0x55aa_55aa_55aa_55aa);
+    pub const DARK_SQUARES: Self = Self(!0x55aa_55aa_55aa_55aa);
+
+    pub fn rank(rank: Rank) -> Self {
+        Self(0xff << (8 * rank as u8))
+    }
+
+    pub fn file(file: File) -> Self {
+        Self(0x0101_0101_0101_0101 << file as u8)
+    }
+
+    pub fn contains(self, sq: Square) -> bool {
+        self.0 & (1 << sq as u8) != 0
+    }
+
+    pub fn insert(&mut self, sq: Square) {
+        self.0 |= 1 << sq as u8;
+    }
+
+    pub fn remove(&mut self, sq: Square) {
+        self.0 &= !(1 << sq as u8);
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn count(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    // Whether more than one square is set
+    pub fn has_many(self) -> bool {
+        self.0 & self.0.wrapping_sub(1) != 0
+    }
+
+    // Lowest index square in the set
+    pub fn first(self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Square::try_from(self.0.trailing_zeros() as u8).ok()
+        }
+    }
+
+    // Highest index square in the set
+    pub fn last(self) -> Option<Square> {
+        if self.is_empty() {
+            None
+        } else {
+            Square::try_from(63 - self.0.leading_zeros() as u8).ok()
+        }
+    }
+
+    // Remove and return the lowest index square
+    pub fn pop_first(&mut self) -> Option<Square> {
+        let sq = self.first()?;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+
+    // Shift every square one rank towards the opponent of `color`
+    pub fn up(self, color: Color) -> Self {
+        match color {
+            Color::White => Self(self.0 << 8),
+            Color::Black => Self(self.0 >> 8),
+        }
+    }
+
+    pub fn down(self, color: Color) -> Self {
+        self.up(!color)
+    }
+
+    // Shift every square one file left or right from the view of `color`,
+    // dropping squares which would wrap around the board.
+    pub fn left(self, color: Color) -> Self {
+        self.right(!color)
+    }
+
+    pub fn right(self, color: Color) -> Self {
+        match color {
+            Color::White => Self((self & !Self::file(File::H)).0 << 1),
+            Color::Black => Self((self & !Self::file(File::A)).0 >> 1),
+        }
+    }
+
+    pub fn iter(self) -> BitboardIter {
+        BitboardIter { bits: self.0 }
+    }
+}
+
+impl From<Square> for Bitboard {
+    fn from(sq: Square) -> Self {
+        Self(1 << sq as u8)
+    }
+}
+
+impl BitAnd for Bitboard {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl BitOr for Bitboard {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitXor for Bitboard {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self(self.0 ^ rhs.0)
+    }
+}
+
+impl Not for Bitboard {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self(!self.0)
+    }
+}
+
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitXorAssign for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Self) {
+        self.0 ^= rhs.0;
+    }
+}
+
+impl FromIterator<Square> for Bitboard {
+    fn from_iter<I: IntoIterator<Item = Square>>(iter: I) -> Self {
+        let mut bb = Self::EMPTY;
+        for sq in iter {
+            bb.insert(sq);
+        }
+        bb
+    }
+}
+
+impl IntoIterator for Bitboard {
+    type Item = Square;
+    type IntoIter = BitboardIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl Iterator for BitboardIter {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bb = Bitboard(self.bits);
+        let sq = bb.pop_first();
+        self.bits = bb.0;
+        sq
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.len();
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for BitboardIter {
+    fn len(&self) -> usize {
+        self.bits.count_ones() as usize
+    }
+}
+
+impl fmt::Debug for Bitboard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Bitboard({:#018x})", self.0)?;
+        for i in (0u8..8).rev() {
+            write!(f, "{} |", i + 1)?;
+            for j in 0..8 {
+                let sq = Square::try_from((i, j)).unwrap();
+                write!(f, " {}", if self.contains(sq) { 'x' } else { '-' })?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "    a b c d e f g h")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Square::*;
+
+    #[test]
+    fn test_bitboard_set_ops() {
+        let mut bb = Bitboard::EMPTY;
+        assert!(bb.is_empty());
+        bb.insert(A1);
+        bb.insert(E4);
+        bb.insert(H8);
+        assert_eq!(bb.count(), 3);
+        assert!(bb.contains(E4));
+        assert!(!bb.contains(E5));
+        bb.remove(E4);
+        assert_eq!(bb, Bitboard::from(A1) | Bitboard::from(H8));
+        assert_eq!(bb & Bitboard::from(A1), Bitboard::from(A1));
+        assert_eq!(bb ^ Bitboard::from(A1), Bitboard::from(H8));
+        assert_eq!((!bb).count(), 62);
+        assert!(bb.has_many());
+        assert!(!Bitboard::from(A1).has_many());
+    }
+
+    #[test]
+    fn test_bitboard_iter() {
+        let squares = vec![B1, D4, G7, H8];
+        let bb: Bitboard = squares.iter().copied().collect();
+        assert_eq!(bb.iter().len(), 4);
+        assert_eq!(bb.iter().collect::<Vec<_>>(), squares);
+        assert_eq!(bb.first(), Some(B1));
+        assert_eq!(bb.last(), Some(H8));
+        assert_eq!(Bitboard::EMPTY.first(), None);
+        assert_eq!(Bitboard::EMPTY.last(), None);
+        assert_eq!(Bitboard::FULL.iter().count(), 64);
+    }
+
+    #[test]
+    fn test_bitboard_masks() {
+        assert_eq!(Bitboard::rank(Rank::R1), Bitboard(0xff));
+        assert_eq!(Bitboard::rank(Rank::R8).first(), Some(A8));
+        assert_eq!(Bitboard::file(File::A).last(), Some(A8));
+        assert_eq!(Bitboard::file(File::H).first(), Some(H1));
+        assert_eq!(Bitboard::file(File::C).count(), 8);
+        assert!(Bitboard::DARK_SQUARES.contains(A1));
+        assert!(Bitboard::LIGHT_SQUARES.contains(H1));
+        assert!(Bitboard::LIGHT_SQUARES.contains(A8));
+        assert!(Bitboard::DARK_SQUARES.contains(H8));
+        assert_eq!(Bitboard::LIGHT_SQUARES.count(), 32);
+    }
+
+    #[test]
+    fn test_bitboard_shifts() {
+        let bb: Bitboard = [A1, E4, H8].iter().copied().collect();
+        assert_eq!(bb.up(Color::White), [A2, E5].iter().copied().collect());
+        assert_eq!(bb.up(Color::Black), [E3, H7].iter().copied().collect());
+        assert_eq!(bb.down(Color::White), bb.up(Color::Black));
+        assert_eq!(bb.right(Color::White), [B1, F4].iter().copied().collect());
+        assert_eq!(bb.left(Color::White), [D4, G8].iter().copied().collect());
+        assert_eq!(bb.right(Color::Black), bb.left(Color::White));
+        assert_eq!(bb.left(Color::Black), bb.right(Color::White));
+    }
+}
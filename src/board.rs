@@ -3,41 +3,63 @@ use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
 
+use crate::attacks;
+use crate::eval::Psq;
 use crate::moves::StateChange;
+use crate::zobrist;
 use crate::{
-    BoardPiece, CastlingRights, Color, File, Move, MoveType, PieceType, Rank, SidePiece, Square,
+    Bitboard, BoardPiece, CastlingRights, Color, File, Move, MoveType, PieceType, PositionIssue,
+    Rank, SidePiece, Square, TranspositionTable,
 };
 
 const DEFAULT_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 const INIT_FEN_LEN: usize = 8 * 8 + 7 + 1 + 4 + 2 + 2 + 3 + 5;
 const INIT_MOVE_LIST_LEN: usize = 32;
 
+// Which moves a generator produces
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum MoveGen {
+    All,
+    // Captures, en-passant and every promotion, the moves quiescence looks at
+    Captures,
+    // Everything else, including castling
+    Quiets,
+}
+
 #[derive(Clone)]
 pub struct Board {
     array: [BoardPiece; 64],
+    pieces: [Bitboard; 6],
+    colors: [Bitboard; 2],
     pub turn: Color,
     pub ep_file: Option<File>,
     pub castle_rights: CastlingRights,
     pub halfmove_clock: u8,
     pub fullmove_count: u16,
+    // Whether castling is written as the king taking its own rook in UCI
+    // notation, as Chess960 GUIs expect
+    pub chess960: bool,
+    hash: u64,
+    psq: Psq,
 }
 
 impl Board {
     pub fn empty() -> Self {
         Self {
             array: [BoardPiece::Empty; 64],
+            pieces: [Bitboard::EMPTY; 6],
+            colors: [Bitboard::EMPTY; 2],
             turn: Color::White,
             ep_file: None,
             castle_rights: CastlingRights::none(),
             halfmove_clock: 0,
             fullmove_count: 1,
+            chess960: false,
+            hash: zobrist::castling(CastlingRights::none()),
+            psq: Psq::default(),
         }
     }
 
-    pub fn default() -> Self {
-        Self::from_fen(DEFAULT_FEN).unwrap()
-    }
-
     pub fn ep_square(&self) -> Option<Square> {
         self.ep_file.map(|file| {
             let rank = match self.turn {
@@ -53,74 +75,197 @@ impl Board {
     }
 
     pub fn set_piece_at(&mut self, sq: Square, piece: BoardPiece) {
+        if let BoardPiece::Piece(old) = self.array[sq as usize] {
+            self.pieces[old.piece_type() as usize].remove(sq);
+            self.colors[old.color() as usize].remove(sq);
+            self.hash ^= zobrist::piece(old, sq);
+            self.psq.remove(old, sq);
+        }
+        if let BoardPiece::Piece(new) = piece {
+            self.pieces[new.piece_type() as usize].insert(sq);
+            self.colors[new.color() as usize].insert(sq);
+            self.hash ^= zobrist::piece(new, sq);
+            self.psq.add(new, sq);
+        }
         self.array[sq as usize] = piece;
     }
 
-    // Doesn't completely validate on purpose, just some checks here and there.
+    // Zobrist hash of the position, kept up to date by `make_move` and `undo_move`.
+    // After assigning `turn`, `ep_file` or `castle_rights` directly, call
+    // `refresh_hash` to bring it back in sync.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    pub fn refresh_hash(&mut self) {
+        self.hash = self.compute_hash();
+    }
+
+    // Hash the position from scratch
+    pub fn compute_hash(&self) -> u64 {
+        let mut hash = self.state_hash();
+        for sq in self.occupied() {
+            if let BoardPiece::Piece(piece) = self.piece_at(sq) {
+                hash ^= zobrist::piece(piece, sq);
+            }
+        }
+        hash
+    }
+
+    // Hash of everything apart from piece placement. The en-passant file only
+    // counts if a pawn could capture there, so positions which only differ by a
+    // useless en-passant square hash the same.
+    fn state_hash(&self) -> u64 {
+        let mut hash = zobrist::castling(self.castle_rights);
+        if self.turn == Color::Black {
+            hash ^= zobrist::black_to_move();
+        }
+        if let Some(ep_sq) = self.ep_square() {
+            let capturers = attacks::pawn_attacks(ep_sq, !self.turn);
+            if !(capturers & self.pieces_of(PieceType::Pawn, self.turn)).is_empty() {
+                hash ^= zobrist::ep_file(ep_sq.file());
+            }
+        }
+        hash
+    }
+
+    // Material and piece-square totals for `eval::evaluate`, kept up to date
+    // by `set_piece_at`
+    pub(crate) fn psq(&self) -> Psq {
+        self.psq
+    }
+
+    // Squares of all pieces of a type, of either color
+    pub fn pieces(&self, piece_type: PieceType) -> Bitboard {
+        self.pieces[piece_type as usize]
+    }
+
+    pub fn pieces_of(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        self.pieces(piece_type) & self.occupied_by(color)
+    }
+
+    pub fn occupied_by(&self, color: Color) -> Bitboard {
+        self.colors[color as usize]
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[Color::White as usize] | self.colors[Color::Black as usize]
+    }
+
+    // Doesn't completely validate on purpose, just some checks here and there,
+    // see `from_fen_strict`.
     pub fn from_fen(fen: &str) -> Result<Self, FenError> {
-        let fen_vec: Vec<&str> = fen.split_ascii_whitespace().collect();
-        if fen_vec.len() != 6 {
-            return Err(FenError);
+        let fields = fen_fields(fen);
+        if let Some(&(offset, text)) = fields.get(FenField::ALL.len()) {
+            return Err(FenError::TrailingText {
+                token: text.to_string(),
+                offset,
+            });
         }
+        let field = |field: FenField| {
+            let &(offset, text) = fields
+                .get(field as usize)
+                .ok_or(FenError::MissingField(field))?;
+            Ok(FenToken {
+                field,
+                text,
+                offset,
+            })
+        };
         let mut board = Self::empty();
         // 1. Piece placement
-        let ranks: Vec<&str> = fen_vec[0].rsplit('/').collect();
-        if ranks.len() != 8 {
-            return Err(FenError);
-        }
-        // Construct board
-        let mut sq = 0u8;
-        for rank in ranks.iter() {
-            let prev_sq = sq;
-            for letter in rank.chars() {
-                let piece = {
-                    if let Ok(spiece) = SidePiece::try_from(letter) {
-                        BoardPiece::Piece(spiece)
-                    } else {
-                        let n = letter.to_digit(9).ok_or(())?;
-                        sq += n as u8 - 1;
-                        BoardPiece::Empty
-                    }
-                };
-                let square = Square::try_from(sq).unwrap();
-                board.set_piece_at(square, piece);
-                sq += 1;
-            }
-            if prev_sq + 8 != sq {
-                return Err(FenError);
-            }
-        }
+        board.parse_placement(field(FenField::Placement)?)?;
         // 2. Side to move
-        board.turn = match fen_vec[1] {
+        let side = field(FenField::Side)?;
+        board.turn = match side.text {
             "w" => Color::White,
             "b" => Color::Black,
-            _ => return Err(FenError),
+            _ => return Err(side.error(FenReason::BadSide)),
         };
         // 3. Castling rights
-        board.castle_rights = fen_vec[2].parse()?;
+        board.castle_rights = parse_castling(field(FenField::Castling)?)?;
         // 4. En passant
+        let ep = field(FenField::EnPassant)?;
         board.ep_file = {
-            if fen_vec[3] == "-" {
+            if ep.text == "-" {
                 None
             } else {
-                let sq = Square::from_str(fen_vec[3])?;
-                if (board.turn == Color::White && sq.rank() != Rank::R6)
-                    || (board.turn == Color::Black && sq.rank() != Rank::R3)
-                {
-                    return Err(FenError);
+                let sq = Square::from_str(ep.text).map_err(|_| ep.error(FenReason::BadSquare))?;
+                let rank = match board.turn {
+                    Color::White => Rank::R6,
+                    Color::Black => Rank::R3,
+                };
+                if sq.rank() != rank {
+                    return Err(ep.error(FenReason::WrongEpRank(rank)));
                 }
                 Some(sq.file())
             }
         };
-        // 5. Halfmove clock
-        board.halfmove_clock = fen_vec[4].parse().map_err(|_| FenError)?;
-        // 6. Fullmove counter
-        board.fullmove_count = fen_vec[5].parse().map_err(|_| FenError)?;
+        // 5. Halfmove clock, and 6. fullmove counter, which are often left out
+        if let Ok(halfmove) = field(FenField::HalfmoveClock) {
+            board.halfmove_clock = halfmove.number()?;
+        }
+        if let Ok(fullmove) = field(FenField::FullmoveCount) {
+            board.fullmove_count = fullmove.number()?;
+        }
+        board.refresh_hash();
         Ok(board)
     }
 
+    fn parse_placement(&mut self, placement: FenToken) -> Result<(), FenError> {
+        let rank_count = placement.text.split('/').count();
+        if rank_count != 8 {
+            return Err(placement.error(FenReason::RankCount(rank_count)));
+        }
+        // Ranks are listed from the eighth down
+        let mut start = 0;
+        for (text, rank) in placement.text.split('/').zip((0u8..8).rev()) {
+            let rank = Rank::try_from(rank).unwrap();
+            let rank_token = placement.slice(start, text.len());
+            let mut file = 0;
+            for (i, letter) in text.char_indices() {
+                if let Some(n) = letter.to_digit(10) {
+                    if !(1..=8).contains(&n) {
+                        let token = rank_token.slice(i, letter.len_utf8());
+                        return Err(token.error(FenReason::BadEmptyCount(letter)));
+                    }
+                    file += n as u8;
+                } else {
+                    let piece = SidePiece::try_from(letter).map_err(|_| {
+                        rank_token
+                            .slice(i, letter.len_utf8())
+                            .error(FenReason::UnknownPiece(letter))
+                    })?;
+                    if file < 8 {
+                        let sq = Square::from((rank, File::try_from(file).unwrap()));
+                        self.set_piece_at(sq, BoardPiece::Piece(piece));
+                    }
+                    file += 1;
+                }
+                if file > 8 {
+                    return Err(rank_token.error(FenReason::TooManyFiles(rank)));
+                }
+            }
+            if file < 8 {
+                return Err(rank_token.error(FenReason::TooFewFiles(rank)));
+            }
+            start += text.len() + 1;
+        }
+        Ok(())
+    }
+
     // Convert board to FEN
     pub fn to_fen(&self) -> String {
+        self.fen(false)
+    }
+
+    // Like `to_fen`, but with castling rights as the files of the rooks, like
+    // `HAha`, as in Shredder-FEN
+    pub fn to_shredder_fen(&self) -> String {
+        self.fen(true)
+    }
+
+    fn fen(&self, shredder: bool) -> String {
         let mut fen = String::with_capacity(INIT_FEN_LEN);
         // 1. Piece placement
         let mut empty = 0;
@@ -152,7 +297,11 @@ impl Board {
             Color::Black => 'b',
         });
         // 3. Castling rights
-        fen.push_str(&format!(" {}", self.castle_rights));
+        if shredder {
+            fen.push_str(&format!(" {}", self.castle_rights.to_shredder()));
+        } else {
+            fen.push_str(&format!(" {}", self.castle_rights));
+        }
         // 4. En passant
         match self.ep_square() {
             None => fen.push_str(" -"),
@@ -165,25 +314,26 @@ impl Board {
         fen
     }
 
+    // Sanity checks on moves given to `make_move`, in debug builds only.
+    // Moves from untrusted input should go through `try_make_move` instead.
     fn debug_validate_move(&self, mv: Move) {
-        // TODO: Change assertions to debug
         let from_bpiece = self.piece_at(mv.from());
         let to_bpiece = self.piece_at(mv.to());
 
         if let BoardPiece::Piece(piece) = from_bpiece {
-            assert_eq!(piece.color(), self.turn, "Cannot move enemy piece");
+            debug_assert_eq!(piece.color(), self.turn, "Cannot move enemy piece");
         } else {
-            unreachable!("A piece must be moved");
+            debug_assert!(false, "A piece must be moved");
         }
         if let BoardPiece::Piece(piece) = to_bpiece {
-            assert_eq!(piece.color(), !self.turn, "Cannot capture own piece");
+            debug_assert_eq!(piece.color(), !self.turn, "Cannot capture own piece");
         }
 
         match mv.move_type() {
             MoveType::Normal => {}
 
             MoveType::DoublePush => {
-                assert_eq!(
+                debug_assert_eq!(
                     mv.from().file(),
                     mv.to().file(),
                     "Double pawn push cannot change file"
@@ -191,12 +341,12 @@ impl Board {
             }
 
             MoveType::EnPassant => {
-                assert_eq!(
+                debug_assert_eq!(
                     Some(mv.to().file()),
                     self.ep_file,
                     "En-passant file must match board"
                 );
-                assert_eq!(
+                debug_assert_eq!(
                     mv.from().rank(),
                     match self.turn {
                         Color::White => Rank::R5,
@@ -204,7 +354,7 @@ impl Board {
                     },
                     "En-passant must be from rank 5 (white) or 4 (black)"
                 );
-                assert_eq!(
+                debug_assert_eq!(
                     mv.to().rank(),
                     match self.turn {
                         Color::White => Rank::R6,
@@ -212,31 +362,41 @@ impl Board {
                     },
                     "En-passant must be to rank 6 (white) or 3 (black)"
                 );
-                assert_eq!(
+                debug_assert_eq!(
                     (mv.from().file() as i8 - mv.to().file() as i8).abs(),
                     1,
                     "En-passant must be a single diagonal step"
                 );
-                assert_eq!(
+                debug_assert_eq!(
                     to_bpiece,
                     BoardPiece::Empty,
                     "En-passant to location must be empty"
                 );
                 if let Some(ep_pawn_sq) = mv.to().down(self.turn) {
-                    assert_eq!(
+                    debug_assert_eq!(
                         self.piece_at(ep_pawn_sq),
                         BoardPiece::piece(PieceType::Pawn, !self.turn),
                         "Must be an enemy pawn behind en-passant square"
                     );
                 } else {
-                    unreachable!("Invalid en-passant square");
+                    debug_assert!(false, "Invalid en-passant square");
                 }
             }
 
-            MoveType::Castle => {}
+            MoveType::Castle => {
+                debug_assert_eq!(
+                    from_bpiece,
+                    BoardPiece::piece(PieceType::King, self.turn),
+                    "Only the king can castle"
+                );
+                debug_assert!(
+                    matches!(mv.to().file(), File::C | File::G) && mv.from().file() == File::E,
+                    "Castling must move king from E-file to C-file or G-file"
+                );
+            }
 
             MoveType::Promotion(promo) => {
-                assert_eq!(
+                debug_assert_eq!(
                     mv.to().rank(),
                     match self.turn {
                         Color::White => Rank::R8,
@@ -244,7 +404,7 @@ impl Board {
                     },
                     "Promotion cannot occur on non-terminal rank"
                 );
-                assert!(
+                debug_assert!(
                     matches!(
                         promo,
                         PieceType::Rook | PieceType::Bishop | PieceType::Knight | PieceType::Queen
@@ -255,6 +415,45 @@ impl Board {
         }
     }
 
+    // Plays `mv` if it is legal, or says why it isn't and leaves the board
+    // untouched. Unlike `make_move`, this is safe to call with moves from
+    // untrusted input.
+    pub fn try_make_move(&mut self, mv: Move) -> Result<StateChange, MoveError> {
+        self.validate_move(mv)?;
+        Ok(self.make_move(mv))
+    }
+
+    // Checks that `mv` is legal
+    pub fn validate_move(&self, mv: Move) -> Result<(), MoveError> {
+        let piece = match self.piece_at(mv.from()) {
+            BoardPiece::Piece(piece) => piece,
+            BoardPiece::Empty => return Err(MoveError::NoPiece),
+        };
+        if piece.color() != self.turn {
+            return Err(MoveError::WrongColor);
+        }
+        let is_pawn = piece.piece_type() == PieceType::Pawn;
+        let last_rank = matches!(mv.to().rank(), Rank::R1 | Rank::R8);
+        let bad_promotion = match mv.move_type() {
+            MoveType::Promotion(PieceType::Pawn) | MoveType::Promotion(PieceType::King) => true,
+            MoveType::Promotion(_) => !is_pawn || !last_rank,
+            _ => is_pawn && last_rank,
+        };
+        if bad_promotion {
+            return Err(MoveError::BadPromotion);
+        }
+        if !self
+            .pseudo_moves(MoveGen::All, Bitboard::from(mv.from()))
+            .contains(&mv)
+        {
+            return Err(MoveError::IllegalDestination);
+        }
+        if !self.is_legal(mv) {
+            return Err(MoveError::LeavesKingInCheck);
+        }
+        Ok(())
+    }
+
     pub fn make_move(&mut self, mv: Move) -> StateChange {
         self.debug_validate_move(mv);
         let from_bpiece = self.piece_at(mv.from());
@@ -264,7 +463,10 @@ impl Board {
             captured: to_bpiece,
             last_ep_file: self.ep_file,
             last_castle_rights: self.castle_rights,
+            last_halfmove_clock: self.halfmove_clock,
+            last_hash: self.hash,
         };
+        self.hash ^= self.state_hash();
         self.set_piece_at(mv.to(), from_bpiece);
         self.set_piece_at(mv.from(), BoardPiece::Empty);
         self.ep_file = None;
@@ -287,8 +489,10 @@ impl Board {
             }
 
             MoveType::Castle => {
-                // FIXME: Implement castling
-                unimplemented!("Make castle move");
+                // Move rook to other side of king
+                let (rook_from, rook_to) = Self::castle_rook_squares(mv);
+                self.set_piece_at(rook_to, self.piece_at(rook_from));
+                self.set_piece_at(rook_from, BoardPiece::Empty);
             }
 
             MoveType::Promotion(promo) => {
@@ -297,22 +501,35 @@ impl Board {
             }
         }
 
-        // FIXME: Increment halfmove clock
+        // Moving a king or rook, or capturing a rook, loses castling rights
+        self.castle_rights.remove_square(mv.from());
+        self.castle_rights.remove_square(mv.to());
+
+        // Pawn moves and captures are irreversible and reset the clock
+        if from_bpiece == BoardPiece::piece(PieceType::Pawn, self.turn)
+            || to_bpiece != BoardPiece::Empty
+        {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
         if self.turn == Color::Black {
             self.fullmove_count += 1;
         }
         self.turn = !self.turn;
+        self.hash ^= self.state_hash();
         state
     }
 
     pub fn undo_move(&mut self, state: StateChange) {
         let mv = state.last_move;
         self.turn = !self.turn;
-        // FIXME: Restore halfmove clock
+        self.halfmove_clock = state.last_halfmove_clock;
         if self.turn == Color::Black {
             self.fullmove_count -= 1;
         }
         self.ep_file = state.last_ep_file;
+        self.castle_rights = state.last_castle_rights;
         self.set_piece_at(mv.from(), self.piece_at(mv.to()));
         self.set_piece_at(mv.to(), state.captured);
 
@@ -329,8 +546,10 @@ impl Board {
             }
 
             MoveType::Castle => {
-                // TODO: Implement undo castling
-                unimplemented!("Undo castle move");
+                // Return rook to its corner
+                let (rook_from, rook_to) = Self::castle_rook_squares(mv);
+                self.set_piece_at(rook_from, self.piece_at(rook_to));
+                self.set_piece_at(rook_to, BoardPiece::Empty);
             }
 
             MoveType::Promotion(_) => {
@@ -338,173 +557,389 @@ impl Board {
                 self.set_piece_at(mv.from(), BoardPiece::piece(PieceType::Pawn, self.turn));
             }
         }
+        self.hash = state.last_hash;
+    }
+
+    // Square of the king of `color`, if it is on the board
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        self.pieces_of(PieceType::King, color).first()
+    }
+
+    // Squares of enemy pieces giving check to the side to move
+    pub fn checkers(&self) -> Bitboard {
+        match self.king_square(self.turn) {
+            Some(king_sq) => self.attackers(king_sq, !self.turn),
+            None => Bitboard::EMPTY,
+        }
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        match self.king_square(self.turn) {
+            Some(king_sq) => self.is_attacked(king_sq, !self.turn),
+            None => false,
+        }
+    }
+
+    // Whether `color` can't possibly checkmate, whatever the opponent plays
+    pub fn has_insufficient_material(&self, color: Color) -> bool {
+        let ours = self.occupied_by(color);
+        let theirs = self.occupied_by(!color);
+        let heavy = self.pieces(PieceType::Pawn)
+            | self.pieces(PieceType::Rook)
+            | self.pieces(PieceType::Queen);
+        if !(ours & heavy).is_empty() {
+            return false;
+        }
+        let knights = self.pieces_of(PieceType::Knight, color);
+        let bishops = self.pieces_of(PieceType::Bishop, color);
+        if knights.is_empty() && bishops.is_empty() {
+            return true;
+        }
+        // Any enemy piece but the king may end up boxing the king in, even if
+        // only with the opponent's help
+        let kings = self.pieces(PieceType::King);
+        if !knights.is_empty() {
+            return knights.count() == 1 && bishops.is_empty() && (theirs & !kings).is_empty();
+        }
+        // Bishops all on one square color can never attack the other color, so
+        // mate needs an enemy piece other than a bishop to block on it
+        let all_bishops = self.pieces(PieceType::Bishop);
+        let one_color = (all_bishops & Bitboard::LIGHT_SQUARES).is_empty()
+            || (all_bishops & Bitboard::DARK_SQUARES).is_empty();
+        let blockers = theirs & !kings & !all_bishops;
+        one_color && blockers.is_empty()
+    }
+
+    // Dead position where neither side can checkmate, such as K vs K, KB vs K,
+    // KN vs K, or kings with bishops all on the same square color
+    pub fn is_insufficient_material(&self) -> bool {
+        self.has_insufficient_material(Color::White) && self.has_insufficient_material(Color::Black)
+    }
+
+    // Pseudo-legal moves which don't leave the own king in check.
+    // Each candidate is tried on a scratch board, which catches pins, discovered
+    // checks through en-passant, and kings walking along a checking ray.
+    pub fn gen_legal_moves(&self) -> Vec<Move> {
+        self.legal_moves(MoveGen::All, Bitboard::FULL)
+    }
+
+    // Legal captures, en-passant captures and promotions
+    pub fn gen_captures(&self) -> Vec<Move> {
+        self.legal_moves(MoveGen::Captures, Bitboard::FULL)
+    }
+
+    // Legal moves which aren't in `gen_captures`
+    pub fn gen_quiets(&self) -> Vec<Move> {
+        self.legal_moves(MoveGen::Quiets, Bitboard::FULL)
+    }
+
+    // Whether `mv` is legal here, generating only the moving piece's moves.
+    // Useful for moves from elsewhere, like the transposition table.
+    pub(crate) fn is_legal(&self, mv: Move) -> bool {
+        self.legal_moves(MoveGen::All, Bitboard::from(mv.from()))
+            .contains(&mv)
+    }
+
+    fn legal_moves(&self, gen: MoveGen, movers: Bitboard) -> Vec<Move> {
+        let mut moves = self.pseudo_moves(gen, movers);
+        let king_sq = match self.king_square(self.turn) {
+            Some(sq) => sq,
+            None => return moves,
+        };
+        // Only the king can escape a double check
+        if self.checkers().has_many() {
+            moves.retain(|&mv| mv.from() == king_sq);
+        }
+        let mut board = self.clone();
+        moves.retain(|&mv| {
+            let state = board.make_move(mv);
+            let to_check = if mv.from() == king_sq {
+                mv.to()
+            } else {
+                king_sq
+            };
+            let legal = !board.is_attacked(to_check, board.turn);
+            board.undo_move(state);
+            legal
+        });
+        moves
+    }
+
+    // Number of leaf nodes of the legal move tree to `depth` plies
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        let moves = self.gen_legal_moves();
+        if depth == 1 {
+            return moves.len() as u64;
+        }
+        let mut nodes = 0;
+        for mv in moves {
+            let state = self.make_move(mv);
+            nodes += self.perft(depth - 1);
+            self.undo_move(state);
+        }
+        nodes
+    }
+
+    // Perft reusing the counts of transposed subtrees from `table`, which is
+    // much faster for deep runs. The table shouldn't hold search entries.
+    pub fn perft_cached(&mut self, depth: u8, table: &TranspositionTable) -> u64 {
+        if depth <= 1 {
+            return self.perft(depth);
+        }
+        if let Some(nodes) = table.probe_perft(self.hash(), depth) {
+            return nodes;
+        }
+        let mut nodes = 0;
+        for mv in self.gen_legal_moves() {
+            let state = self.make_move(mv);
+            nodes += self.perft_cached(depth - 1, table);
+            self.undo_move(state);
+        }
+        table.store_perft(self.hash(), depth, nodes);
+        nodes
+    }
+
+    // Perft split by each legal root move, useful to track down a wrong count
+    pub fn perft_divide(&mut self, depth: u8) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut divide = Vec::new();
+        for mv in self.gen_legal_moves() {
+            let state = self.make_move(mv);
+            divide.push((mv, self.perft(depth - 1)));
+            self.undo_move(state);
+        }
+        divide
     }
 
     pub fn gen_pseudo_moves(&self) -> Vec<Move> {
+        self.pseudo_moves(MoveGen::All, Bitboard::FULL)
+    }
+
+    // Moves of the pieces on `movers`
+    fn pseudo_moves(&self, gen: MoveGen, movers: Bitboard) -> Vec<Move> {
         let mut moves = Vec::with_capacity(INIT_MOVE_LIST_LEN);
-        for sq in Square::iter() {
-            if let BoardPiece::Piece(piece) = self.piece_at(sq) {
-                if piece.color() == self.turn {
-                    match piece.piece_type() {
-                        PieceType::Pawn => self.gen_pawn_moves(sq, &mut moves),
-                        PieceType::Knight => self.gen_knight_moves(sq, &mut moves),
-                        PieceType::King => self.gen_king_moves(sq, &mut moves),
-                        PieceType::Rook => self.gen_rook_moves(sq, &mut moves),
-                        PieceType::Bishop => self.gen_bishop_moves(sq, &mut moves),
-                        PieceType::Queen => self.gen_queen_moves(sq, &mut moves),
-                    }
-                }
-            }
-        }
+        // Squares pieces other than pawns may move to
+        let targets = match gen {
+            MoveGen::All => !self.occupied_by(self.turn),
+            MoveGen::Captures => self.occupied_by(!self.turn),
+            MoveGen::Quiets => !self.occupied(),
+        };
+        self.gen_pawn_moves(gen, movers, &mut moves);
+        self.gen_knight_moves(movers, targets, &mut moves);
+        self.gen_bishop_moves(movers, targets, &mut moves);
+        self.gen_rook_moves(movers, targets, &mut moves);
+        self.gen_queen_moves(movers, targets, &mut moves);
+        self.gen_king_moves(movers, targets, gen != MoveGen::Captures, &mut moves);
         moves
     }
 
-    fn gen_pawn_moves(&self, sq: Square, moves: &mut Vec<Move>) {
+    fn gen_pawn_moves(&self, gen: MoveGen, movers: Bitboard, moves: &mut Vec<Move>) {
         fn add_promo_moves(from: Square, to: Square, moves: &mut Vec<Move>) {
-            for &promo in [PieceType::Queen, PieceType::Knight].iter() {
+            let promos = [
+                PieceType::Queen,
+                PieceType::Knight,
+                PieceType::Rook,
+                PieceType::Bishop,
+            ];
+            for &promo in promos.iter() {
                 moves.push(Move::new(from, to, MoveType::Promotion(promo)));
             }
         }
 
-        let up = sq.up(self.turn).expect("Invalid rank for pawn");
-        if self.piece_at(up) == BoardPiece::Empty {
-            if let Some(up_up) = up.up(self.turn) {
-                // Move forward
-                moves.push(Move::normal(sq, up));
-                // Double push
-                if ((self.turn == Color::White && sq.rank() == Rank::R2)
-                    || (self.turn == Color::Black && sq.rank() == Rank::R7))
-                    && self.piece_at(up_up) == BoardPiece::Empty
-                {
-                    moves.push(Move::new(sq, up_up, MoveType::DoublePush));
-                }
-            } else {
-                //  Pawn promotion
-                add_promo_moves(sq, up, moves);
+        let pawns = self.pieces_of(PieceType::Pawn, self.turn) & movers;
+        let empty = !self.occupied();
+        let (double_push_rank, promo_rank) = match self.turn {
+            Color::White => (Rank::R4, Rank::R8),
+            Color::Black => (Rank::R5, Rank::R1),
+        };
+        let promo_rank = Bitboard::rank(promo_rank);
+        let pushes = pawns.up(self.turn) & empty;
+
+        if gen != MoveGen::Captures {
+            // Move forward
+            for to in pushes & !promo_rank {
+                let from = to.down(self.turn).unwrap();
+                moves.push(Move::normal(from, to));
+            }
+            // Double push
+            for to in pushes.up(self.turn) & empty & Bitboard::rank(double_push_rank) {
+                let from = to.down(self.turn).and_then(|x| x.down(self.turn)).unwrap();
+                moves.push(Move::new(from, to, MoveType::DoublePush));
             }
         }
+        if gen == MoveGen::Quiets {
+            return;
+        }
+        // Pawn promotion
+        for to in pushes & promo_rank {
+            let from = to.down(self.turn).unwrap();
+            add_promo_moves(from, to, moves);
+        }
 
-        for diag in [up.left(self.turn), up.right(self.turn)]
-            .iter()
-            .filter_map(|&x| x)
-        {
-            if let BoardPiece::Piece(capture) = self.piece_at(diag) {
-                if capture.color() != self.turn {
-                    if up.rank() == Rank::R1 || up.rank() == Rank::R1 {
-                        // Pawn promotion and capture
-                        add_promo_moves(sq, diag, moves);
-                    } else {
-                        // Capture diagonally
-                        moves.push(Move::normal(sq, diag));
-                    }
+        let enemies = self.occupied_by(!self.turn);
+        for from in pawns {
+            let attacks = attacks::pawn_attacks(from, self.turn);
+            for to in attacks & enemies {
+                if promo_rank.contains(to) {
+                    // Pawn promotion and capture
+                    add_promo_moves(from, to, moves);
+                } else {
+                    // Capture diagonally
+                    moves.push(Move::normal(from, to));
                 }
             }
             // En-passant capture
-            if self.ep_square() == Some(diag) {
-                moves.push(Move::new(sq, diag, MoveType::EnPassant));
+            if let Some(ep_sq) = self.ep_square() {
+                if attacks.contains(ep_sq) {
+                    moves.push(Move::new(from, ep_sq, MoveType::EnPassant));
+                }
             }
         }
     }
 
-    fn gen_knight_moves(&self, sq: Square, moves: &mut Vec<Move>) {
-        for to in [
-            (-2, -1),
-            (-2, 1),
-            (-1, -2),
-            (-1, 2),
-            (1, -2),
-            (1, 2),
-            (2, -1),
-            (2, 1),
-        ]
-        .iter()
-        .map(|(dr, df)| (sq.rank() as i8 + dr, sq.file() as i8 + df))
-        .filter_map(|coords| Square::try_from(coords).ok())
-        {
-            if match self.piece_at(to) {
-                BoardPiece::Empty => true,
-                BoardPiece::Piece(piece) => piece.color() != self.turn,
-            } {
-                moves.push(Move::normal(sq, to));
-            }
+    fn gen_knight_moves(&self, movers: Bitboard, targets: Bitboard, moves: &mut Vec<Move>) {
+        for from in self.pieces_of(PieceType::Knight, self.turn) & movers {
+            add_normal_moves(from, attacks::knight_attacks(from) & targets, moves);
         }
     }
 
-    fn gen_king_moves(&self, sq: Square, moves: &mut Vec<Move>) {
-        for vert in [sq.up(Color::White), Some(sq), sq.down(Color::White)]
-            .iter()
-            .filter_map(|&x| x)
+    fn gen_bishop_moves(&self, movers: Bitboard, targets: Bitboard, moves: &mut Vec<Move>) {
+        for from in self.pieces_of(PieceType::Bishop, self.turn) & movers {
+            let attacks = attacks::bishop_attacks(from, self.occupied());
+            add_normal_moves(from, attacks & targets, moves);
+        }
+    }
+
+    fn gen_rook_moves(&self, movers: Bitboard, targets: Bitboard, moves: &mut Vec<Move>) {
+        for from in self.pieces_of(PieceType::Rook, self.turn) & movers {
+            let attacks = attacks::rook_attacks(from, self.occupied());
+            add_normal_moves(from, attacks & targets, moves);
+        }
+    }
+
+    fn gen_queen_moves(&self, movers: Bitboard, targets: Bitboard, moves: &mut Vec<Move>) {
+        for from in self.pieces_of(PieceType::Queen, self.turn) & movers {
+            let attacks = attacks::queen_attacks(from, self.occupied());
+            add_normal_moves(from, attacks & targets, moves);
+        }
+    }
+
+    fn gen_king_moves(
+        &self,
+        movers: Bitboard,
+        targets: Bitboard,
+        castles: bool,
+        moves: &mut Vec<Move>,
+    ) {
+        let sq = match self.king_square(self.turn) {
+            Some(sq) if movers.contains(sq) => sq,
+            _ => return,
+        };
+        add_normal_moves(sq, attacks::king_attacks(sq) & targets, moves);
+
+        // Castling, only from the king's starting square and never out of check
+        let back_rank = match self.turn {
+            Color::White => Rank::R1,
+            Color::Black => Rank::R8,
+        };
+        if !castles || sq != Square::from((back_rank, File::E)) || self.is_attacked(sq, !self.turn)
         {
-            for to in [
-                vert.left(Color::White),
-                if sq != vert { Some(vert) } else { None },
-                vert.right(Color::White),
-            ]
-            .iter()
-            .filter_map(|&x| x)
+            return;
+        }
+        for &(has_right, rook_file, between, king_path) in [
+            (
+                self.castle_rights.king_side(self.turn),
+                File::H,
+                &[File::F, File::G][..],
+                &[File::F, File::G][..],
+            ),
+            (
+                self.castle_rights.queen_side(self.turn),
+                File::A,
+                &[File::B, File::C, File::D][..],
+                &[File::D, File::C][..],
+            ),
+        ]
+        .iter()
+        {
+            if !has_right
+                || self.piece_at(Square::from((back_rank, rook_file)))
+                    != BoardPiece::piece(PieceType::Rook, self.turn)
             {
-                if match self.piece_at(to) {
-                    BoardPiece::Empty => true,
-                    BoardPiece::Piece(piece) => piece.color() != self.turn,
-                } {
-                    moves.push(Move::normal(sq, to));
-                }
+                continue;
+            }
+            // Squares between king and rook must be empty
+            if between
+                .iter()
+                .any(|&file| self.piece_at(Square::from((back_rank, file))) != BoardPiece::Empty)
+            {
+                continue;
             }
+            // King cannot pass through or land on an attacked square
+            if king_path
+                .iter()
+                .any(|&file| self.is_attacked(Square::from((back_rank, file)), !self.turn))
+            {
+                continue;
+            }
+            let to = Square::from((back_rank, king_path[king_path.len() - 1]));
+            moves.push(Move::new(sq, to, MoveType::Castle));
         }
-        // TODO: Generate castling moves
     }
 
-    fn gen_rook_moves(&self, sq: Square, moves: &mut Vec<Move>) {
-        for next in [Square::up, Square::down, Square::left, Square::right].iter() {
-            let mut prev = sq;
-            while let Some(to) = next(prev, Color::White) {
-                prev = to;
-                match self.piece_at(to) {
-                    BoardPiece::Piece(piece) => {
-                        if piece.color() != self.turn {
-                            // Capture
-                            moves.push(Move::normal(sq, to));
-                        }
-                        break;
-                    }
-                    BoardPiece::Empty => {
-                        // Move to empty square
-                        moves.push(Move::normal(sq, to));
-                    }
-                }
-            }
+    // Rook origin and destination squares for a castle move, given by the king
+    pub(crate) fn castle_rook_squares(mv: Move) -> (Square, Square) {
+        let rank = mv.from().rank();
+        match mv.to().file() {
+            File::G => (Square::from((rank, File::H)), Square::from((rank, File::F))),
+            File::C => (Square::from((rank, File::A)), Square::from((rank, File::D))),
+            _ => unreachable!("Invalid castle destination"),
         }
     }
 
-    fn gen_bishop_moves(&self, sq: Square, moves: &mut Vec<Move>) {
-        for horizontal in [Square::left, Square::right].iter() {
-            for vertical in [Square::up, Square::down].iter() {
-                let mut prev = sq;
-                while let Some(to) =
-                    vertical(prev, Color::White).and_then(|x| horizontal(x, Color::White))
-                {
-                    prev = to;
-                    match self.piece_at(to) {
-                        BoardPiece::Piece(piece) => {
-                            if piece.color() != self.turn {
-                                // Capture
-                                moves.push(Move::normal(sq, to));
-                            }
-                            break;
-                        }
-                        BoardPiece::Empty => {
-                            // Move to empty square
-                            moves.push(Move::normal(sq, to));
-                        }
-                    }
-                }
-            }
-        }
+    // Whether any piece of color `by` attacks `sq`
+    pub fn is_attacked(&self, sq: Square, by: Color) -> bool {
+        !self.attackers(sq, by).is_empty()
+    }
+
+    // Squares of all pieces of either color attacking `sq`
+    pub fn attackers_to(&self, sq: Square) -> Bitboard {
+        self.attackers(sq, Color::White) | self.attackers(sq, Color::Black)
+    }
+
+    // Squares of all pieces of color `by` attacking `sq`.
+    // Each piece pattern is symmetric, so look outwards from `sq` for a piece
+    // which could make the same step back.
+    fn attackers(&self, sq: Square, by: Color) -> Bitboard {
+        let occupied = self.occupied();
+        let queens = self.pieces(PieceType::Queen);
+        let straight = self.pieces(PieceType::Rook) | queens;
+        let diagonal = self.pieces(PieceType::Bishop) | queens;
+        // Pawns attack diagonally upwards, so look diagonally downwards
+        let attackers = (attacks::pawn_attacks(sq, !by) & self.pieces(PieceType::Pawn))
+            | (attacks::knight_attacks(sq) & self.pieces(PieceType::Knight))
+            | (attacks::king_attacks(sq) & self.pieces(PieceType::King))
+            | (attacks::rook_attacks(sq, occupied) & straight)
+            | (attacks::bishop_attacks(sq, occupied) & diagonal);
+        attackers & self.occupied_by(by)
     }
+}
 
-    fn gen_queen_moves(&self, sq: Square, moves: &mut Vec<Move>) {
-        self.gen_rook_moves(sq, moves);
-        self.gen_bishop_moves(sq, moves);
+// Moves or captures from `from` to each of `targets`
+fn add_normal_moves(from: Square, targets: Bitboard, moves: &mut Vec<Move>) {
+    for to in targets {
+        moves.push(Move::normal(from, to));
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::from_fen(DEFAULT_FEN).unwrap()
     }
 }
 
@@ -563,8 +998,152 @@ impl fmt::Display for Board {
     }
 }
 
-#[derive(Copy, Clone)]
-pub struct FenError;
+// Part of a FEN field, and where it starts in the FEN
+#[derive(Clone, Copy)]
+struct FenToken<'a> {
+    field: FenField,
+    text: &'a str,
+    offset: usize,
+}
+
+impl<'a> FenToken<'a> {
+    fn slice(&self, start: usize, len: usize) -> FenToken<'a> {
+        FenToken {
+            text: &self.text[start..start + len],
+            offset: self.offset + start,
+            ..*self
+        }
+    }
+
+    fn error(&self, reason: FenReason) -> FenError {
+        FenError::InvalidField {
+            field: self.field,
+            token: self.text.to_string(),
+            offset: self.offset,
+            reason,
+        }
+    }
+
+    fn number<T: FromStr>(&self) -> Result<T, FenError> {
+        self.text
+            .parse()
+            .map_err(|_| self.error(FenReason::BadNumber))
+    }
+}
+
+// Whitespace separated fields of a FEN, with their byte offsets
+fn fen_fields(fen: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in fen.char_indices().chain(std::iter::once((fen.len(), ' '))) {
+        match (start, c.is_ascii_whitespace()) {
+            (Some(offset), true) => {
+                fields.push((offset, &fen[offset..i]));
+                start = None;
+            }
+            (None, false) => start = Some(i),
+            _ => {}
+        }
+    }
+    fields
+}
+
+// Castling rights given as `KQkq`, or as the files of the castling rooks like
+// `HAha` in Shredder-FEN and X-FEN. Only rooks starting in the corners can
+// castle, so other files are refused.
+fn parse_castling(castling: FenToken) -> Result<CastlingRights, FenError> {
+    // White king side, white queen side, black king side, black queen side
+    let mut rights = [false; 4];
+    if castling.text != "-" {
+        for (i, letter) in castling.text.char_indices() {
+            let color_index = if letter.is_ascii_uppercase() { 0 } else { 2 };
+            let side_index = match letter.to_ascii_lowercase() {
+                'k' | 'h' => Ok(0),
+                'q' | 'a' => Ok(1),
+                'b'..='g' => Err(FenReason::UnsupportedCastling(letter)),
+                _ => Err(FenReason::UnknownCastling(letter)),
+            };
+            let reason = match side_index {
+                Ok(side_index) if !rights[color_index + side_index] => {
+                    rights[color_index + side_index] = true;
+                    continue;
+                }
+                Ok(_) => FenReason::RepeatedCastling(letter),
+                Err(reason) => reason,
+            };
+            return Err(castling.slice(i, letter.len_utf8()).error(reason));
+        }
+    }
+    Ok(CastlingRights::new(
+        rights[0], rights[1], rights[2], rights[3],
+    ))
+}
+
+// Fields of a FEN string, in order
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FenField {
+    Placement,
+    Side,
+    Castling,
+    EnPassant,
+    HalfmoveClock,
+    FullmoveCount,
+}
+
+impl FenField {
+    pub const ALL: [FenField; 6] = [
+        FenField::Placement,
+        FenField::Side,
+        FenField::Castling,
+        FenField::EnPassant,
+        FenField::HalfmoveClock,
+        FenField::FullmoveCount,
+    ];
+}
+
+// What is wrong with a FEN field
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FenReason {
+    // The placement doesn't have 8 ranks
+    RankCount(usize),
+    TooManyFiles(Rank),
+    TooFewFiles(Rank),
+    // Neither a piece letter nor a digit
+    UnknownPiece(char),
+    // A digit outside 1 to 8, which can't count the empty squares in a rank
+    BadEmptyCount(char),
+    // Neither `w` nor `b`
+    BadSide,
+    UnknownCastling(char),
+    // The file of a rook which doesn't start in a corner
+    UnsupportedCastling(char),
+    RepeatedCastling(char),
+    BadSquare,
+    // The en passant square isn't on the given rank, behind a pawn which just
+    // moved two squares
+    WrongEpRank(Rank),
+    BadNumber,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FenError {
+    // The FEN ends before this field
+    MissingField(FenField),
+    // Something follows the last field
+    TrailingText {
+        token: String,
+        offset: usize,
+    },
+    // `token`, found at byte `offset` of the FEN, is invalid for `field`
+    InvalidField {
+        field: FenField,
+        token: String,
+        offset: usize,
+        reason: FenReason,
+    },
+    // The FEN parsed, but the position can't come up in a game
+    IllegalPosition(Vec<PositionIssue>),
+}
 
 impl Error for FenError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
@@ -572,21 +1151,105 @@ impl Error for FenError {
     }
 }
 
-impl From<()> for FenError {
-    fn from(_: ()) -> Self {
-        Self
+impl fmt::Display for FenField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            FenField::Placement => "piece placement",
+            FenField::Side => "side to move",
+            FenField::Castling => "castling",
+            FenField::EnPassant => "en passant",
+            FenField::HalfmoveClock => "halfmove clock",
+            FenField::FullmoveCount => "fullmove number",
+        };
+        write!(f, "{}", name)
     }
 }
 
-impl fmt::Debug for FenError {
+impl fmt::Display for FenReason {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "FenError(\"{}\")", self)
+        let rank = |rank: Rank| rank as u8 + 1;
+        match *self {
+            FenReason::RankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            FenReason::TooManyFiles(r) => write!(f, "too many files in rank {}", rank(r)),
+            FenReason::TooFewFiles(r) => write!(f, "too few files in rank {}", rank(r)),
+            FenReason::UnknownPiece(c) => write!(f, "unknown piece `{}`", c),
+            FenReason::BadEmptyCount(c) => {
+                write!(f, "empty square count `{}` must be from 1 to 8", c)
+            }
+            FenReason::BadSide => write!(f, "expected `w` or `b`"),
+            FenReason::UnknownCastling(c) => write!(f, "unknown castling right `{}`", c),
+            FenReason::UnsupportedCastling(c) => {
+                write!(f, "castling with the rook on file `{}` isn't supported", c)
+            }
+            FenReason::RepeatedCastling(c) => write!(f, "castling right `{}` repeated", c),
+            FenReason::BadSquare => write!(f, "expected a square or `-`"),
+            FenReason::WrongEpRank(r) => {
+                write!(f, "en passant square must be on rank {}", rank(r))
+            }
+            FenReason::BadNumber => write!(f, "expected a number"),
+        }
     }
 }
 
 impl fmt::Display for FenError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Invalid FEN string")
+        match self {
+            FenError::MissingField(field) => write!(f, "Invalid FEN: missing {} field", field),
+            FenError::TrailingText { token, offset } => write!(
+                f,
+                "Invalid FEN: unexpected `{}` at byte {} after the last field",
+                token, offset
+            ),
+            FenError::InvalidField {
+                field,
+                token,
+                offset,
+                reason,
+            } => write!(
+                f,
+                "Invalid FEN {} `{}` at byte {}: {}",
+                field, token, offset, reason
+            ),
+            FenError::IllegalPosition(issues) => {
+                let issues: Vec<String> = issues.iter().map(|issue| issue.to_string()).collect();
+                write!(f, "Illegal FEN position: {}", issues.join(", "))
+            }
+        }
+    }
+}
+
+// Why `Board::try_make_move` refused a move
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoveError {
+    // Nothing on the from square
+    NoPiece,
+    // The piece belongs to the side not to move
+    WrongColor,
+    // The piece can't move to the target square that way
+    IllegalDestination,
+    // The move would leave or put the own king in check
+    LeavesKingInCheck,
+    // Promotion to a pawn or king, by something other than a pawn or short of
+    // the last rank, or a pawn reaching it without promoting
+    BadPromotion,
+}
+
+impl Error for MoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            MoveError::NoPiece => "No piece on the from square",
+            MoveError::WrongColor => "Piece belongs to the side not to move",
+            MoveError::IllegalDestination => "Piece cannot move to that square",
+            MoveError::LeavesKingInCheck => "Move leaves the king in check",
+            MoveError::BadPromotion => "Invalid promotion",
+        };
+        write!(f, "{}", msg)
     }
 }
 
@@ -600,4 +1263,586 @@ mod tests {
         let fen = board.to_fen();
         assert_eq!(fen, DEFAULT_FEN);
     }
+
+    #[test]
+    fn test_fen_errors() {
+        let invalid = |fen: &str| match Board::from_fen(fen) {
+            Err(FenError::InvalidField {
+                field,
+                token,
+                offset,
+                reason,
+            }) => (field, token, offset, reason),
+            other => panic!("unexpected result {:?}", other.map(|board| board.to_fen())),
+        };
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1"),
+            (
+                FenField::Placement,
+                "RNBQKBNRR".to_string(),
+                35,
+                FenReason::TooManyFiles(Rank::R1)
+            )
+        );
+        assert_eq!(
+            invalid("rnbqkbnr/pppppppp/8/8/3x4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"),
+            (
+                FenField::Placement,
+                "x".to_string(),
+                23,
+                FenReason::UnknownPiece('x')
+            )
+        );
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8/7 w - - 0 1").3,
+            FenReason::TooFewFiles(Rank::R1)
+        );
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8/9 w - - 0 1"),
+            (
+                FenField::Placement,
+                "9".to_string(),
+                14,
+                FenReason::BadEmptyCount('9')
+            )
+        );
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8/08 w - - 0 1").3,
+            FenReason::BadEmptyCount('0')
+        );
+        assert_eq!(
+            invalid("8/8/8/8/8/8/8 w - - 0 1").3,
+            FenReason::RankCount(7)
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 x - - 0 1"),
+            (FenField::Side, "x".to_string(), 20, FenReason::BadSide)
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w KQxq - 0 1"),
+            (
+                FenField::Castling,
+                "x".to_string(),
+                24,
+                FenReason::UnknownCastling('x')
+            )
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w KK - 0 1").3,
+            FenReason::RepeatedCastling('K')
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w - e3 0 1"),
+            (
+                FenField::EnPassant,
+                "e3".to_string(),
+                24,
+                FenReason::WrongEpRank(Rank::R6)
+            )
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w - e9 0 1").3,
+            FenReason::BadSquare
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w - - 300 1"),
+            (
+                FenField::HalfmoveClock,
+                "300".to_string(),
+                26,
+                FenReason::BadNumber
+            )
+        );
+        assert_eq!(
+            invalid("4k3/8/8/8/8/8/8/4K3 w - - 0 x").0,
+            FenField::FullmoveCount
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w -").err(),
+            Some(FenError::MissingField(FenField::EnPassant))
+        );
+        assert_eq!(
+            Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra").err(),
+            Some(FenError::TrailingText {
+                token: "extra".to_string(),
+                offset: 30
+            })
+        );
+        assert_eq!(
+            Board::from_fen("8/8/8/8/3x4/8/8/8 w - - 0 1")
+                .err()
+                .unwrap()
+                .to_string(),
+            "Invalid FEN piece placement `x` at byte 9: unknown piece `x`"
+        );
+    }
+
+    #[test]
+    fn test_fen_variants() {
+        // Shredder-FEN and X-FEN castling letters
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1").unwrap();
+        assert_eq!(board.castle_rights, CastlingRights::all());
+        assert_eq!(
+            board.to_shredder_fen(),
+            "r3k2r/8/8/8/8/8/8/R3K2R w HAha - 0 1"
+        );
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b Kha - 0 1").unwrap();
+        assert_eq!(board.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R b Kkq - 0 1");
+        assert_eq!(
+            board.to_shredder_fen(),
+            "r3k2r/8/8/8/8/8/8/R3K2R b Hha - 0 1"
+        );
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.to_shredder_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        let err = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K1R1 w GAha - 0 1").unwrap_err();
+        assert!(matches!(
+            err,
+            FenError::InvalidField {
+                reason: FenReason::UnsupportedCastling('G'),
+                ..
+            }
+        ));
+        let err = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KH - 0 1").unwrap_err();
+        assert!(matches!(
+            err,
+            FenError::InvalidField {
+                reason: FenReason::RepeatedCastling('H'),
+                ..
+            }
+        ));
+
+        // Missing clocks default to the start of a game
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 0 1");
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 7").unwrap();
+        assert_eq!(board.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 7 1");
+    }
+
+    fn sorted_moves(mut moves: Vec<Move>) -> Vec<String> {
+        let mut moves: Vec<String> = moves.drain(..).map(|mv| mv.to_string()).collect();
+        moves.sort();
+        moves
+    }
+
+    fn sorted_squares(squares: Bitboard) -> Vec<Square> {
+        squares.iter().collect()
+    }
+
+    #[test]
+    fn test_hash_transposition() {
+        let mut board = Board::default();
+        let start_hash = board.hash();
+        assert_eq!(start_hash, board.compute_hash());
+        for &(from, to) in [
+            (Square::G1, Square::F3),
+            (Square::G8, Square::F6),
+            (Square::F3, Square::G1),
+            (Square::F6, Square::G8),
+        ]
+        .iter()
+        {
+            board.make_move(Move::normal(from, to));
+            assert_eq!(board.hash(), board.compute_hash());
+        }
+        assert_eq!(board.halfmove_clock, 4);
+        // Same position, but the move counters differ
+        assert_eq!(board.hash(), start_hash);
+        assert_ne!(board.to_fen(), DEFAULT_FEN);
+    }
+
+    #[test]
+    fn test_halfmove_clock() {
+        let fen = "4k3/8/8/8/3p4/8/4P3/4K3 w - - 7 20";
+        let mut board = Board::from_fen(fen).unwrap();
+        let king_move = board.make_move(Move::normal(Square::E1, Square::F1));
+        assert_eq!(board.halfmove_clock, 8);
+        let pawn_move = board.make_move(Move::normal(Square::D4, Square::D3));
+        assert_eq!(board.halfmove_clock, 0);
+        let capture = board.make_move(Move::normal(Square::E2, Square::D3));
+        assert_eq!(board.halfmove_clock, 0);
+        board.undo_move(capture);
+        board.undo_move(pawn_move);
+        assert_eq!(board.halfmove_clock, 8);
+        board.undo_move(king_move);
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_hash_state() {
+        let fen = "4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1";
+        let board = Board::from_fen(fen).unwrap();
+        let black = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 b - - 0 1").unwrap();
+        assert_ne!(board.hash(), black.hash());
+        let castle = Board::from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w K - 0 1").unwrap();
+        assert_ne!(board.hash(), castle.hash());
+
+        // En-passant only counts when a pawn can capture
+        let mut board = Board::from_fen(fen).unwrap();
+        board.make_move(Move::new(Square::E2, Square::E4, MoveType::DoublePush));
+        let ep_hash = board.hash();
+        assert_eq!(ep_hash, board.compute_hash());
+        board.ep_file = None;
+        assert_ne!(ep_hash, board.compute_hash());
+        let mut board = Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        board.make_move(Move::new(Square::E2, Square::E4, MoveType::DoublePush));
+        let ep_hash = board.hash();
+        board.ep_file = None;
+        assert_eq!(ep_hash, board.compute_hash());
+    }
+
+    #[test]
+    fn test_is_attacked() {
+        let board = Board::default();
+        assert!(board.is_attacked(Square::F3, Color::White));
+        assert!(!board.is_attacked(Square::F3, Color::Black));
+        assert!(board.is_attacked(Square::D6, Color::Black));
+        assert!(!board.is_attacked(Square::E4, Color::White));
+        assert!(!board.is_attacked(Square::E1, Color::Black));
+        // Blocked slider
+        let board = Board::from_fen("4k3/8/8/8/1r1P3K/8/8/8 w - - 0 1").unwrap();
+        assert!(board.is_attacked(Square::D4, Color::Black));
+        assert!(!board.is_attacked(Square::H4, Color::Black));
+        assert!(board.is_attacked(Square::B8, Color::Black));
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        let board = Board::from_fen("4k3/8/2n2b2/3p4/4Q3/8/8/4R1K1 w - - 0 1").unwrap();
+        assert_eq!(
+            sorted_squares(board.attackers_to(Square::E4)),
+            vec![Square::E1, Square::D5]
+        );
+        assert_eq!(
+            sorted_squares(board.attackers_to(Square::D4)),
+            vec![Square::E4, Square::C6, Square::F6]
+        );
+        assert_eq!(
+            sorted_squares(board.attackers_to(Square::E5)),
+            vec![Square::E4, Square::C6, Square::F6]
+        );
+        assert!(board.attackers_to(Square::A8).is_empty());
+    }
+
+    #[test]
+    fn test_insufficient_material() {
+        for &fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2K3 b - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/1B6/2B1K3 w - - 0 1",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(board.is_insufficient_material(), "{}", fen);
+        }
+        for &fen in [
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1",
+            "1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/BB2K3 w - - 0 1",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            assert!(!board.is_insufficient_material(), "{}", fen);
+        }
+        // Only one side can mate
+        let board = Board::from_fen("4k3/8/8/8/8/8/p7/1N2K3 w - - 0 1").unwrap();
+        assert!(!board.has_insufficient_material(Color::White));
+        assert!(!board.has_insufficient_material(Color::Black));
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1").unwrap();
+        assert!(!board.has_insufficient_material(Color::White));
+        assert!(board.has_insufficient_material(Color::Black));
+        // Enemy pieces can box the king in for a knight or bishop to mate
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/q1N1K3 w - - 0 1").unwrap();
+        assert!(!board.has_insufficient_material(Color::White));
+        let board = Board::from_fen("r3k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert!(!board.has_insufficient_material(Color::White));
+    }
+
+    #[test]
+    fn test_checkers() {
+        let board = Board::default();
+        assert!(!board.is_in_check());
+        assert!(board.checkers().is_empty());
+        // Double check from rook and knight
+        let board = Board::from_fen("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert!(board.is_in_check());
+        assert_eq!(
+            sorted_squares(board.checkers()),
+            vec![Square::E1, Square::D6]
+        );
+        // Pawn check
+        let board = Board::from_fen("8/8/8/8/8/3k4/4P3/4K3 b - - 0 1").unwrap();
+        assert_eq!(board.checkers(), Bitboard::from(Square::E2));
+    }
+
+    #[test]
+    fn test_gen_legal_moves_default() {
+        let board = Board::default();
+        assert_eq!(board.gen_legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn test_gen_legal_moves_double_check() {
+        let board = Board::from_fen("4k3/8/3N4/8/8/8/3q4/4R1K1 b - - 0 1").unwrap();
+        assert_eq!(
+            sorted_moves(board.gen_legal_moves()),
+            vec!["e8->d7", "e8->d8", "e8->f8"]
+        );
+    }
+
+    #[test]
+    fn test_gen_legal_moves_pinned() {
+        // Knight on E4 is pinned to king by rook, bishop can block or capture
+        let board = Board::from_fen("4r1k1/8/8/8/4N3/8/8/2B1K3 w - - 0 1").unwrap();
+        let moves = board.gen_legal_moves();
+        assert!(moves.iter().all(|mv| mv.from() != Square::E4));
+        // Rook on E2 is pinned but can slide along the pin
+        let board = Board::from_fen("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            sorted_moves(board.gen_legal_moves()),
+            vec![
+                "e1->d1", "e1->d2", "e1->f1", "e1->f2", "e2->e3", "e2->e4", "e2->e5", "e2->e6",
+                "e2->e7", "e2->e8"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gen_legal_moves_ep_discovered_check() {
+        // Capturing en-passant would remove both pawns from the king's rank
+        let board = Board::from_fen("8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1").unwrap();
+        let moves = board.gen_legal_moves();
+        assert!(moves.iter().all(|mv| mv.move_type() != MoveType::EnPassant));
+        assert!(board
+            .gen_pseudo_moves()
+            .iter()
+            .any(|mv| mv.move_type() == MoveType::EnPassant));
+    }
+
+    #[test]
+    fn test_gen_captures_and_quiets() {
+        for fen in [
+            DEFAULT_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/8/8/KPp4r/8/8/8/4k3 w - c6 0 1",
+            "4k3/8/3N4/8/8/8/3q4/4R1K1 b - - 0 1",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            let captures = board.gen_captures();
+            let quiets = board.gen_quiets();
+            for mv in captures.iter() {
+                let is_capture = board.piece_at(mv.to()) != BoardPiece::Empty
+                    || mv.move_type() == MoveType::EnPassant;
+                let is_promotion = matches!(mv.move_type(), MoveType::Promotion(_));
+                assert!(is_capture || is_promotion, "{:?} in {}", mv, fen);
+            }
+            for mv in quiets.iter() {
+                assert_eq!(board.piece_at(mv.to()), BoardPiece::Empty);
+                assert!(!matches!(
+                    mv.move_type(),
+                    MoveType::Promotion(_) | MoveType::EnPassant
+                ));
+            }
+            // Captures and quiets don't overlap, so together they must be every move
+            let legal = board.gen_legal_moves();
+            assert_eq!(captures.len() + quiets.len(), legal.len(), "{}", fen);
+            assert!(legal
+                .iter()
+                .all(|mv| captures.contains(mv) || quiets.contains(mv)));
+        }
+
+        let board = Board::from_fen("8/8/8/3pP3/8/8/8/k3K3 w - d6 0 1").unwrap();
+        assert_eq!(sorted_moves(board.gen_captures()), vec!["e5->d6"]);
+    }
+
+    #[test]
+    fn test_is_legal() {
+        let board = Board::from_fen("4r1k1/8/8/8/4N3/8/8/2B1K3 w - - 0 1").unwrap();
+        for mv in board.gen_legal_moves() {
+            assert!(board.is_legal(mv));
+        }
+        // Pinned knight, a bishop move that isn't one, and the other side's move
+        assert!(!board.is_legal(Move::normal(Square::E4, Square::F6)));
+        assert!(!board.is_legal(Move::normal(Square::C1, Square::C4)));
+        assert!(!board.is_legal(Move::normal(Square::G8, Square::H8)));
+        assert!(!board.is_legal(Move::new(Square::E1, Square::G1, MoveType::Castle)));
+    }
+
+    #[test]
+    fn test_gen_promotions() {
+        fn promotions(board: &Board) -> Vec<String> {
+            let mut promos: Vec<String> = board
+                .gen_legal_moves()
+                .into_iter()
+                .filter_map(|mv| match mv.move_type() {
+                    MoveType::Promotion(promo) => Some(format!("{}{}", mv.to(), promo)),
+                    _ => None,
+                })
+                .collect();
+            promos.sort();
+            promos
+        }
+
+        // Pushes and captures, for both colors
+        let board = Board::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            promotions(&board),
+            vec!["a8b", "a8n", "a8q", "a8r", "b8b", "b8n", "b8q", "b8r"]
+        );
+        let board = Board::from_fen("4k3/8/8/8/8/8/p7/1N2K3 b - - 0 1").unwrap();
+        assert_eq!(
+            promotions(&board),
+            vec!["a1b", "a1n", "a1q", "a1r", "b1b", "b1n", "b1q", "b1r"]
+        );
+    }
+
+    fn castle_moves(board: &Board) -> Vec<Move> {
+        board
+            .gen_pseudo_moves()
+            .into_iter()
+            .filter(|mv| mv.move_type() == MoveType::Castle)
+            .collect()
+    }
+
+    #[test]
+    fn test_gen_castle_moves() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![
+                Move::new(Square::E1, Square::G1, MoveType::Castle),
+                Move::new(Square::E1, Square::C1, MoveType::Castle),
+            ]
+        );
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b Qk - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![Move::new(Square::E8, Square::G8, MoveType::Castle)]
+        );
+        // Blocked on queen side by knight on B1
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![Move::new(Square::E1, Square::G1, MoveType::Castle)]
+        );
+    }
+
+    #[test]
+    fn test_gen_castle_moves_attacked() {
+        // King in check
+        let board = Board::from_fen("4r1k1/8/8/8/8/8/8/R3K2R w KQ - 0 1").unwrap();
+        assert!(castle_moves(&board).is_empty());
+        // Passing through F1 attacked by bishop
+        let board = Board::from_fen("6k1/8/8/8/8/8/6b1/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![Move::new(Square::E1, Square::C1, MoveType::Castle)]
+        );
+        // Landing on C1 attacked by knight, B1 attacked doesn't matter
+        let board = Board::from_fen("6k1/8/8/8/8/1n6/8/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![Move::new(Square::E1, Square::G1, MoveType::Castle)]
+        );
+        let board = Board::from_fen("6k1/8/8/8/8/n7/8/R3K2R w Q - 0 1").unwrap();
+        assert_eq!(
+            castle_moves(&board),
+            vec![Move::new(Square::E1, Square::C1, MoveType::Castle)]
+        );
+    }
+
+    #[test]
+    fn test_try_make_move() {
+        use Square::*;
+        // The knight on d2 is pinned, and the rook covers f1 and f2
+        let fen = "4kr2/1P6/8/b7/8/8/3N4/4K1N1 w - - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let errors = [
+            (Move::normal(A1, A2), MoveError::NoPiece),
+            (Move::normal(E8, D8), MoveError::WrongColor),
+            (Move::normal(G1, G3), MoveError::IllegalDestination),
+            (
+                Move::new(E1, G1, MoveType::Castle),
+                MoveError::IllegalDestination,
+            ),
+            (Move::normal(D2, F3), MoveError::LeavesKingInCheck),
+            (Move::normal(E1, F2), MoveError::LeavesKingInCheck),
+            (Move::normal(B7, B8), MoveError::BadPromotion),
+            (
+                Move::new(B7, B8, MoveType::Promotion(PieceType::King)),
+                MoveError::BadPromotion,
+            ),
+            (
+                Move::new(G1, H3, MoveType::Promotion(PieceType::Queen)),
+                MoveError::BadPromotion,
+            ),
+        ];
+        for &(mv, err) in errors.iter() {
+            assert_eq!(board.try_make_move(mv), Err(err), "{:?}", mv);
+            assert_eq!(board.to_fen(), fen);
+        }
+
+        let promo = Move::new(B7, B8, MoveType::Promotion(PieceType::Rook));
+        let state = board.try_make_move(promo).unwrap();
+        assert_eq!(
+            board.piece_at(B8),
+            BoardPiece::piece(PieceType::Rook, Color::White)
+        );
+        board.undo_move(state);
+        assert!(board.try_make_move(Move::normal(E1, E2)).is_ok());
+        assert_eq!(board.to_fen(), "4kr2/1P6/8/b7/8/8/3NK3/6N1 b - - 1 1");
+    }
+
+    #[test]
+    fn test_make_undo_castle() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let state = board.make_move(Move::new(Square::E1, Square::G1, MoveType::Castle));
+        assert_eq!(board.to_fen(), "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        board.undo_move(state);
+        assert_eq!(board.to_fen(), fen);
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        let state = board.make_move(Move::new(Square::E8, Square::C8, MoveType::Castle));
+        assert_eq!(board.to_fen(), "2kr3r/8/8/8/8/8/8/R3K2R w KQ - 1 2");
+        board.undo_move(state);
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn test_castle_rights_update() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        let mut board = Board::from_fen(fen).unwrap();
+        // Rook captures rook, both lose their king side
+        let state = board.make_move(Move::normal(Square::H1, Square::H8));
+        assert_eq!(
+            board.castle_rights,
+            CastlingRights::new(false, true, false, true)
+        );
+        board.undo_move(state);
+        assert_eq!(board.castle_rights, CastlingRights::all());
+        // King move loses both sides
+        board.make_move(Move::normal(Square::E1, Square::E2));
+        assert_eq!(
+            board.castle_rights,
+            CastlingRights::new(false, false, true, true)
+        );
+        board.make_move(Move::normal(Square::A8, Square::A7));
+        assert_eq!(
+            board.castle_rights,
+            CastlingRights::new(false, false, true, false)
+        );
+    }
 }
@@ -0,0 +1,284 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{Board, Game, Move, Search, SearchInfo, SearchLimits, TranspositionTable};
+
+// How often an infinite search checks whether it was stopped
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+// Game state shared by the protocol front-ends, with a search running on a
+// worker thread so commands can still be read while it thinks
+pub struct Engine {
+    game: Game,
+    // Kept between searches, and only cleared for a new game
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+    // Whether castling is written as the king taking its own rook, carried
+    // over to every new position
+    chess960: bool,
+    // Whether the search tries promotions to rook and bishop
+    under_promotions: bool,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        Engine {
+            game: Game::default(),
+            tt: Arc::new(TranspositionTable::default()),
+            stop: Arc::new(AtomicBool::new(false)),
+            worker: None,
+            chess960: false,
+            under_promotions: true,
+        }
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    // Stops any running search first, since it would be searching a stale position
+    pub fn game_mut(&mut self) -> &mut Game {
+        self.stop();
+        &mut self.game
+    }
+
+    pub fn new_game(&mut self) {
+        self.set_position(Board::default());
+        self.table_mut().clear();
+    }
+
+    pub fn table(&self) -> &TranspositionTable {
+        &self.tt
+    }
+
+    // Stops any running search first, since it shares the table
+    pub fn table_mut(&mut self) -> &mut TranspositionTable {
+        self.stop();
+        Arc::get_mut(&mut self.tt).expect("table is only shared with the search thread")
+    }
+
+    pub fn set_position(&mut self, mut board: Board) {
+        board.chess960 = self.chess960;
+        *self.game_mut() = Game::from_board(board);
+    }
+
+    // Plays a move given in coordinate notation, or returns `None` if it isn't legal
+    pub fn play_move(&mut self, text: &str) -> Option<Move> {
+        let mv = self.game.board.parse_uci_move(text).ok()?;
+        self.game_mut().make_move(mv);
+        Some(mv)
+    }
+
+    // Sets an option by name, as sent by a GUI, returning whether it was
+    // recognised with a valid value. Supports `Hash`, the table size in
+    // megabytes, `Clear Hash`, `UCI_Chess960` and `UnderPromotions`.
+    pub fn set_option(&mut self, name: &str, value: &str) -> bool {
+        if name.eq_ignore_ascii_case("Hash") {
+            match value.trim().parse::<usize>() {
+                Ok(size_mb) if size_mb > 0 => self.table_mut().resize(size_mb),
+                _ => return false,
+            }
+        } else if name.eq_ignore_ascii_case("Clear Hash") {
+            self.table_mut().clear();
+        } else if name.eq_ignore_ascii_case("UCI_Chess960") {
+            match parse_check(value) {
+                Some(enabled) => self.chess960 = enabled,
+                None => return false,
+            }
+            self.game_mut().board.chess960 = self.chess960;
+        } else if name.eq_ignore_ascii_case("UnderPromotions") {
+            match parse_check(value) {
+                Some(enabled) => self.under_promotions = enabled,
+                None => return false,
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    // Starts searching the current position in the background, calling
+    // `on_info` after each iteration and `on_best_move` once done, both from the
+    // worker thread. Any earlier search is stopped first.
+    pub fn go<I, F>(&mut self, limits: SearchLimits, on_info: I, on_best_move: F)
+    where
+        I: FnMut(&SearchInfo) + Send + 'static,
+        F: FnOnce(Option<Move>) + Send + 'static,
+    {
+        self.stop();
+        self.stop.store(false, Ordering::SeqCst);
+        let mut search = Search::new(&self.game, Arc::clone(&self.tt));
+        search.under_promotions = self.under_promotions;
+        let stop = Arc::clone(&self.stop);
+        self.worker = Some(thread::spawn(move || {
+            let info = search.run(limits, &stop, on_info);
+            // An infinite search may only report its move once stopped
+            if limits.infinite {
+                while !stop.load(Ordering::SeqCst) {
+                    thread::sleep(STOP_POLL_INTERVAL);
+                }
+            }
+            on_best_move(info.best_move());
+        }));
+    }
+
+    // Stops the search and waits for it to report its move
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.wait();
+    }
+
+    // Waits for the search to finish on its own
+    pub fn wait(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            worker.join().expect("search thread panicked");
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| !worker.is_finished())
+    }
+}
+
+// Value of a `check` option, `true` or `false` in any case
+fn parse_check(value: &str) -> Option<bool> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MoveType, PieceType, Square};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_play_move() {
+        let mut engine = Engine::new();
+        assert!(engine.play_move("e2e4").is_some());
+        assert!(engine.play_move("e7e5").is_some());
+        assert!(engine.play_move("e4e5").is_none());
+        assert_eq!(engine.game().moves().count(), 2);
+        engine.new_game();
+        assert_eq!(engine.game().moves().count(), 0);
+    }
+
+    #[test]
+    fn test_set_option() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            engine.table().size_mb(),
+            TranspositionTable::DEFAULT_SIZE_MB
+        );
+        assert!(engine.set_option("Hash", "4"));
+        assert_eq!(engine.table().size_mb(), 4);
+        assert!(!engine.set_option("Hash", "0"));
+        assert!(!engine.set_option("Hash", "lots"));
+        assert!(!engine.set_option("Ponder", "true"));
+
+        assert!(engine.set_option("hash", "1"));
+        let limits = SearchLimits {
+            depth: Some(4),
+            ..SearchLimits::default()
+        };
+        engine.go(limits, |_| {}, |_| {});
+        engine.wait();
+        assert!(engine.table().hashfull() > 0);
+        assert!(engine.set_option("Clear Hash", ""));
+        assert_eq!(engine.table().hashfull(), 0);
+
+        // Chess960 castling notation applies to the current and later positions
+        assert!(!engine.set_option("UCI_Chess960", "yes"));
+        assert!(engine.set_option("UCI_Chess960", "true"));
+        assert!(engine.game().board.chess960);
+        engine.set_position(Board::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap());
+        assert!(engine.game().board.chess960);
+        assert!(engine.play_move("e1h1").is_some());
+        engine.new_game();
+        assert!(engine.game().board.chess960);
+        assert!(engine.set_option("uci_chess960", "False"));
+        assert!(!engine.game().board.chess960);
+
+        // Only a rook promotion wins, as a queen stalemates
+        let best_move = |engine: &mut Engine| {
+            let (tx, rx) = mpsc::channel();
+            let limits = SearchLimits {
+                depth: Some(3),
+                ..SearchLimits::default()
+            };
+            engine.go(limits, |_| {}, move |best| tx.send(best).unwrap());
+            engine.wait();
+            rx.recv().unwrap().unwrap()
+        };
+        engine.set_position(Board::from_fen("8/k1P5/2K5/8/8/8/8/8 w - - 0 1").unwrap());
+        let rook = Move::new(Square::C7, Square::C8, MoveType::Promotion(PieceType::Rook));
+        assert_eq!(best_move(&mut engine), rook);
+        assert!(!engine.set_option("UnderPromotions", "1"));
+        assert!(engine.set_option("UnderPromotions", "false"));
+        assert!(!matches!(
+            best_move(&mut engine).move_type(),
+            MoveType::Promotion(PieceType::Rook) | MoveType::Promotion(PieceType::Bishop)
+        ));
+    }
+
+    #[test]
+    fn test_go_and_stop() {
+        let mut engine = Engine::new();
+        let (tx, rx) = mpsc::channel();
+        let limits = SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        };
+        engine.go(limits, |_| {}, move |best| tx.send(best).unwrap());
+        assert!(engine.is_searching());
+        assert!(rx.try_recv().is_err());
+        engine.stop();
+        assert!(!engine.is_searching());
+        let best = rx.recv().unwrap().unwrap();
+        assert!(engine.game().board.gen_legal_moves().contains(&best));
+
+        // Iterations are reported as they complete
+        let (tx, rx) = mpsc::channel();
+        let limits = SearchLimits {
+            depth: Some(3),
+            ..SearchLimits::default()
+        };
+        engine.go(limits, move |info| tx.send(info.depth).unwrap(), |_| {});
+        engine.wait();
+        assert_eq!(rx.iter().collect::<Vec<_>>(), vec![1, 2, 3]);
+
+        // Stalemate has no move to report
+        engine.set_position(Board::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap());
+        let (tx, rx) = mpsc::channel();
+        engine.go(
+            SearchLimits::default(),
+            |_| {},
+            move |best| tx.send(best).unwrap(),
+        );
+        engine.wait();
+        assert_eq!(rx.recv().unwrap(), None);
+    }
+}
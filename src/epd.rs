@@ -0,0 +1,361 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Board, FenError, Move};
+
+// Extended Position Description: a position without its move clocks,
+// followed by operations like `bm Nf3; id "test 1";`. Moves are written in
+// SAN, though UCI notation is accepted when reading.
+#[derive(Clone, Debug, Default)]
+pub struct Epd {
+    // Clocks come from the `hmvc` and `fmvn` operations if present
+    pub board: Board,
+    // `bm`: best moves
+    pub best_moves: Vec<Move>,
+    // `am`: moves to avoid
+    pub avoid_moves: Vec<Move>,
+    // `id`: name of the position in its suite
+    pub id: Option<String>,
+    // `c0`: comment
+    pub comment: Option<String>,
+    // `acd`: depth of the analysis
+    pub depth: Option<u32>,
+    // `ce`: evaluation in centipawns for the side to move
+    pub eval: Option<i32>,
+    // `pv`: predicted line
+    pub pv: Vec<Move>,
+    // Any other operations, with their operands as written
+    pub other: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EpdError {
+    InvalidFen(FenError),
+    // A string operand missing its closing quote
+    UnterminatedString,
+    // An operand of `opcode` that doesn't fit it
+    InvalidOperand { opcode: String, operand: String },
+}
+
+impl FromStr for Epd {
+    type Err = EpdError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        // The position is the first four fields
+        let mut fen_len = 0;
+        for _ in 0..4 {
+            let rest = &line[fen_len..];
+            let start = rest.len() - rest.trim_start().len();
+            let field_len = rest[start..]
+                .find(char::is_whitespace)
+                .unwrap_or(rest.len() - start);
+            fen_len += start + field_len;
+        }
+        let board = Board::from_fen(&line[..fen_len]).map_err(EpdError::InvalidFen)?;
+        let mut epd = Epd {
+            board,
+            ..Epd::default()
+        };
+        for (opcode, operands) in parse_operations(&line[fen_len..])? {
+            epd.apply(opcode, operands)?;
+        }
+        Ok(epd)
+    }
+}
+
+impl Epd {
+    fn apply(&mut self, opcode: String, operands: Vec<String>) -> Result<(), EpdError> {
+        let invalid = |operand: &str| invalid_operand(&opcode, operand);
+        match opcode.as_str() {
+            "bm" | "am" => {
+                let moves = operands
+                    .iter()
+                    .map(|text| parse_move(&self.board, text).ok_or_else(|| invalid(text)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if opcode == "bm" {
+                    self.best_moves = moves;
+                } else {
+                    self.avoid_moves = moves;
+                }
+            }
+            "pv" => {
+                let mut board = self.board.clone();
+                self.pv.clear();
+                for text in operands.iter() {
+                    let mv = parse_move(&board, text).ok_or_else(|| invalid(text))?;
+                    board.make_move(mv);
+                    self.pv.push(mv);
+                }
+            }
+            "id" => self.id = Some(single(&opcode, &operands)?.to_string()),
+            "c0" => self.comment = Some(single(&opcode, &operands)?.to_string()),
+            "acd" => self.depth = Some(number(&opcode, &operands)?),
+            "ce" => self.eval = Some(number(&opcode, &operands)?),
+            "hmvc" => {
+                self.board.halfmove_clock = number(&opcode, &operands)?;
+                self.board.refresh_hash();
+            }
+            "fmvn" => self.board.fullmove_count = number(&opcode, &operands)?,
+            _ => self.other.push((opcode, operands)),
+        }
+        Ok(())
+    }
+}
+
+fn invalid_operand(opcode: &str, operand: &str) -> EpdError {
+    EpdError::InvalidOperand {
+        opcode: opcode.to_string(),
+        operand: operand.to_string(),
+    }
+}
+
+// The operand of an operation taking exactly one
+fn single<'a>(opcode: &str, operands: &'a [String]) -> Result<&'a str, EpdError> {
+    match operands {
+        [operand] => Ok(operand),
+        _ => Err(invalid_operand(opcode, &operands.join(" "))),
+    }
+}
+
+fn number<T: FromStr>(opcode: &str, operands: &[String]) -> Result<T, EpdError> {
+    let operand = single(opcode, operands)?;
+    operand
+        .parse()
+        .map_err(|_| invalid_operand(opcode, operand))
+}
+
+// Moves are meant to be SAN, but some suites use UCI notation
+fn parse_move(board: &Board, text: &str) -> Option<Move> {
+    board
+        .parse_san(text)
+        .or_else(|_| board.parse_uci_move(text))
+        .ok()
+}
+
+// Splits `opcode operand...;` operations, with operands either bare words or
+// quoted strings. The last `;` may be left out.
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations = Vec::new();
+    let mut words: Vec<String> = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        match chars.peek() {
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+            }
+            Some(';') | None => {
+                if !words.is_empty() {
+                    let opcode = words.remove(0);
+                    operations.push((opcode, words.split_off(0)));
+                }
+                if chars.next().is_none() {
+                    break;
+                }
+            }
+            Some('"') if !words.is_empty() => {
+                chars.next();
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => string.extend(chars.next()),
+                        Some(c) => string.push(c),
+                        None => return Err(EpdError::UnterminatedString),
+                    }
+                }
+                words.push(string);
+            }
+            Some(_) => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == ';' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                words.push(word);
+            }
+        }
+    }
+    Ok(operations)
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl fmt::Display for Epd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fen = self.board.to_fen();
+        let fields: Vec<&str> = fen.split(' ').take(4).collect();
+        write!(f, "{}", fields.join(" "))?;
+
+        let san = |moves: &[Move]| -> Vec<String> {
+            moves.iter().map(|&mv| self.board.move_to_san(mv)).collect()
+        };
+        if !self.best_moves.is_empty() {
+            write!(f, " bm {};", san(&self.best_moves).join(" "))?;
+        }
+        if !self.avoid_moves.is_empty() {
+            write!(f, " am {};", san(&self.avoid_moves).join(" "))?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, " acd {};", depth)?;
+        }
+        if let Some(eval) = self.eval {
+            write!(f, " ce {};", eval)?;
+        }
+        if !self.pv.is_empty() {
+            let mut board = self.board.clone();
+            let mut line = Vec::new();
+            for &mv in self.pv.iter() {
+                line.push(board.move_to_san(mv));
+                board.make_move(mv);
+            }
+            write!(f, " pv {};", line.join(" "))?;
+        }
+        if self.board.halfmove_clock != 0 {
+            write!(f, " hmvc {};", self.board.halfmove_clock)?;
+        }
+        if self.board.fullmove_count != 1 {
+            write!(f, " fmvn {};", self.board.fullmove_count)?;
+        }
+        if let Some(id) = &self.id {
+            write!(f, " id {};", quote(id))?;
+        }
+        if let Some(comment) = &self.comment {
+            write!(f, " c0 {};", quote(comment))?;
+        }
+        for (opcode, operands) in self.other.iter() {
+            write!(f, " {}", opcode)?;
+            for operand in operands.iter() {
+                // Bare words can't hold spaces or semicolons
+                if operand.is_empty() || operand.contains(|c: char| c.is_whitespace() || c == ';') {
+                    write!(f, " {}", quote(operand))?;
+                } else {
+                    write!(f, " {}", operand)?;
+                }
+            }
+            write!(f, ";")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for EpdError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EpdError::InvalidFen(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EpdError::InvalidFen(err) => write!(f, "Invalid EPD position: {}", err),
+            EpdError::UnterminatedString => write!(f, "Unterminated EPD string"),
+            EpdError::InvalidOperand { opcode, operand } => {
+                write!(f, "Invalid EPD operand for {}: {}", opcode, operand)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MoveType, Square::*};
+
+    #[test]
+    fn test_parse_epd() {
+        let epd: Epd = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - \
+                        bm Bb5 Bc4; am g4; acd 12; ce -15; pv Bb5 a6 Ba4; \
+                        id \"open \\\"1\\\"\"; c0 \"Ruy; or Italian\"; hmvc 2; fmvn 3; \
+                        sv e2e4 \"two words\";"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            epd.board.to_fen(),
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"
+        );
+        assert_eq!(epd.board.hash(), epd.board.compute_hash());
+        assert_eq!(
+            epd.best_moves,
+            vec![Move::normal(F1, B5), Move::normal(F1, C4)]
+        );
+        assert_eq!(
+            epd.avoid_moves,
+            vec![Move::new(G2, G4, MoveType::DoublePush)]
+        );
+        assert_eq!(epd.depth, Some(12));
+        assert_eq!(epd.eval, Some(-15));
+        assert_eq!(
+            epd.pv,
+            vec![
+                Move::normal(F1, B5),
+                Move::normal(A7, A6),
+                Move::normal(B5, A4)
+            ]
+        );
+        assert_eq!(epd.id.as_deref(), Some("open \"1\""));
+        assert_eq!(epd.comment.as_deref(), Some("Ruy; or Italian"));
+        assert_eq!(
+            epd.other,
+            vec![(
+                "sv".to_string(),
+                vec!["e2e4".to_string(), "two words".to_string()]
+            )]
+        );
+
+        // UCI moves, a missing last semicolon, and Shredder castling
+        let epd: Epd = "4k3/8/8/8/8/8/8/R3K2R w HA - bm e1g1".parse().unwrap();
+        assert_eq!(epd.best_moves, vec![Move::new(E1, G1, MoveType::Castle)]);
+        assert_eq!(epd.board.to_fen(), "4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1");
+    }
+
+    #[test]
+    fn test_epd_errors() {
+        assert!(matches!(
+            "4k3/8/8/8/8/8/8/4K3 w".parse::<Epd>(),
+            Err(EpdError::InvalidFen(_))
+        ));
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 w - - bm Ke3;".parse::<Epd>().err(),
+            Some(EpdError::InvalidOperand {
+                opcode: "bm".to_string(),
+                operand: "Ke3".to_string()
+            })
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 w - - acd deep;".parse::<Epd>().err(),
+            Some(EpdError::InvalidOperand {
+                opcode: "acd".to_string(),
+                operand: "deep".to_string()
+            })
+        );
+        assert_eq!(
+            "4k3/8/8/8/8/8/8/4K3 w - - id \"open".parse::<Epd>().err(),
+            Some(EpdError::UnterminatedString)
+        );
+    }
+
+    #[test]
+    fn test_write_epd() {
+        for line in [
+            "4k3/8/8/8/8/8/8/4K3 w - -",
+            "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; \
+             am g4; acd 12; ce -15; pv Bb5 a6 Ba4; hmvc 2; fmvn 3; id \"open \\\"1\\\"\"; \
+             c0 \"Ruy; or Italian\"; sv e2e4 \"two words\";",
+        ]
+        .iter()
+        {
+            let epd: Epd = line.parse().unwrap();
+            assert_eq!(epd.to_string(), *line);
+        }
+    }
+}
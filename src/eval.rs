@@ -0,0 +1,406 @@
+use std::fmt;
+use std::ops::{Add, AddAssign, Neg, Sub, SubAssign};
+
+use crate::{Board, Color, SidePiece, Square};
+
+// Material and piece-square values are the PeSTO tables from
+// https://www.chessprogramming.org/PeSTO%27s_Evaluation_Function, indexed by
+// `PieceType`. Tables are laid out as seen from White, so a8 comes first.
+const MG_VALUES: [i32; 6] = [82, 477, 337, 365, 1025, 0];
+const EG_VALUES: [i32; 6] = [94, 512, 281, 297, 936, 0];
+
+// Rough piece values by `PieceType`, for judging exchanges and ordering moves
+// in the search rather than for evaluation. The king can't be captured, so
+// its value only matters for it being the last to recapture.
+pub(crate) const PIECE_VALUES: [i32; 6] = [100, 500, 300, 300, 900, 0];
+
+// Contribution of each piece type to the game phase, which starts out at 24
+const PHASE_WEIGHTS: [i32; 6] = [0, 2, 1, 1, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+#[rustfmt::skip]
+const MG_TABLES: [[i32; 64]; 6] = [
+    [ // Pawn
+          0,   0,   0,   0,   0,   0,   0,   0,
+         98, 134,  61,  95,  68, 126,  34, -11,
+         -6,   7,  26,  31,  65,  56,  25, -20,
+        -14,  13,   6,  21,  23,  12,  17, -23,
+        -27,  -2,  -5,  12,  17,   6,  10, -25,
+        -26,  -4,  -4, -10,   3,   3,  33, -12,
+        -35,  -1, -20, -23, -15,  24,  38, -22,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [ // Rook
+         32,  42,  32,  51,  63,   9,  31,  43,
+         27,  32,  58,  62,  80,  67,  26,  44,
+         -5,  19,  26,  36,  17,  45,  61,  16,
+        -24, -11,   7,  26,  24,  35,  -8, -20,
+        -36, -26, -12,  -1,   9,  -7,   6, -23,
+        -45, -25, -16, -17,   3,   0,  -5, -33,
+        -44, -16, -20,  -9,  -1,  11,  -6, -71,
+        -19, -13,   1,  17,  16,   7, -37, -26,
+    ],
+    [ // Knight
+       -167, -89, -34, -49,  61, -97, -15,-107,
+        -73, -41,  72,  36,  23,  62,   7, -17,
+        -47,  60,  37,  65,  84, 129,  73,  44,
+         -9,  17,  19,  53,  37,  69,  18,  22,
+        -13,   4,  16,  13,  28,  19,  21,  -8,
+        -23,  -9,  12,  10,  19,  17,  25, -16,
+        -29, -53, -12,  -3,  -1,  18, -14, -19,
+       -105, -21, -58, -33, -17, -28, -19, -23,
+    ],
+    [ // Bishop
+        -29,   4, -82, -37, -25, -42,   7,  -8,
+        -26,  16, -18, -13,  30,  59,  18, -47,
+        -16,  37,  43,  40,  35,  50,  37,  -2,
+         -4,   5,  19,  50,  37,  37,   7,  -2,
+         -6,  13,  13,  26,  34,  12,  10,   4,
+          0,  15,  15,  15,  14,  27,  18,  10,
+          4,  15,  16,   0,   7,  21,  33,   1,
+        -33,  -3, -14, -21, -13, -12, -39, -21,
+    ],
+    [ // Queen
+        -28,   0,  29,  12,  59,  44,  43,  45,
+        -24, -39,  -5,   1, -16,  57,  28,  54,
+        -13, -17,   7,   8,  29,  56,  47,  57,
+        -27, -27, -16, -16,  -1,  17,  -2,   1,
+         -9, -26,  -9, -10,  -2,  -4,   3,  -3,
+        -14,   2, -11,  -2,  -5,   2,  14,   5,
+        -35,  -8,  11,   2,   8,  15,  -3,   1,
+         -1, -18,  -9,  10, -15, -25, -31, -50,
+    ],
+    [ // King
+        -65,  23,  16, -15, -56, -34,   2,  13,
+         29,  -1, -20,  -7,  -8,  -4, -38, -29,
+         -9,  24,   2, -16, -20,   6,  22, -22,
+        -17, -20, -12, -27, -30, -25, -14, -36,
+        -49,  -1, -27, -39, -46, -44, -33, -51,
+        -14, -14, -22, -46, -44, -30, -15, -27,
+          1,   7,  -8, -64, -43, -16,   9,   8,
+        -15,  36,  12, -54,   8, -28,  24,  14,
+    ],
+];
+
+#[rustfmt::skip]
+const EG_TABLES: [[i32; 64]; 6] = [
+    [ // Pawn
+          0,   0,   0,   0,   0,   0,   0,   0,
+        178, 173, 158, 134, 147, 132, 165, 187,
+         94, 100,  85,  67,  56,  53,  82,  84,
+         32,  24,  13,   5,  -2,   4,  17,  17,
+         13,   9,  -3,  -7,  -7,  -8,   3,  -1,
+          4,   7,  -6,   1,   0,  -5,  -1,  -8,
+         13,   8,   8,  10,  13,   0,   2,  -7,
+          0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    [ // Rook
+         13,  10,  18,  15,  12,  12,   8,   5,
+         11,  13,  13,  11,  -3,   3,   8,   3,
+          7,   7,   7,   5,   4,  -3,  -5,  -3,
+          4,   3,  13,   1,   2,   1,  -1,   2,
+          3,   5,   8,   4,  -5,  -6,  -8, -11,
+         -4,   0,  -5,  -1,  -7, -12,  -8, -16,
+         -6,  -6,   0,   2,  -9,  -9, -11,  -3,
+         -9,   2,   3,  -1,  -5, -13,   4, -20,
+    ],
+    [ // Knight
+        -58, -38, -13, -28, -31, -27, -63, -99,
+        -25,  -8, -25,  -2,  -9, -25, -24, -52,
+        -24, -20,  10,   9,  -1,  -9, -19, -41,
+        -17,   3,  22,  22,  22,  11,   8, -18,
+        -18,  -6,  16,  25,  16,  17,   4, -18,
+        -23,  -3,  -1,  15,  10,  -3, -20, -22,
+        -42, -20, -10,  -5,  -2, -20, -23, -44,
+        -29, -51, -23, -15, -22, -18, -50, -64,
+    ],
+    [ // Bishop
+        -14, -21, -11,  -8,  -7,  -9, -17, -24,
+         -8,  -4,   7, -12,  -3, -13,  -4, -14,
+          2,  -8,   0,  -1,  -2,   6,   0,   4,
+         -3,   9,  12,   9,  14,  10,   3,   2,
+         -6,   3,  13,  19,   7,  10,  -3,  -9,
+        -12,  -3,   8,  10,  13,   3,  -7, -15,
+        -14, -18,  -7,  -1,   4,  -9, -15, -27,
+        -23,  -9, -23,  -5,  -9, -16,  -5, -17,
+    ],
+    [ // Queen
+         -9,  22,  22,  27,  27,  19,  10,  20,
+        -17,  20,  32,  41,  58,  25,  30,   0,
+        -20,   6,   9,  49,  47,  35,  19,   9,
+          3,  22,  24,  45,  57,  40,  57,  36,
+        -18,  28,  19,  47,  31,  34,  39,  23,
+        -16, -27,  15,   6,   9,  17,  10,   5,
+        -22, -23, -30, -16, -16, -23, -36, -32,
+        -33, -28, -22, -43,  -5, -32, -20, -41,
+    ],
+    [ // King
+        -74, -35, -18, -18, -11,  15,   4, -17,
+        -12,  17,  14,  17,  17,  38,  23,  11,
+         10,  17,  23,  15,  20,  45,  44,  13,
+         -8,  22,  24,  27,  26,  33,  26,   3,
+        -18,  -4,  21,  24,  27,  23,   9, -11,
+        -19,  -3,  11,  21,  23,  16,   7,  -9,
+        -27, -11,   4,  13,  14,   4,  -5, -17,
+        -53, -34, -21, -11, -28, -14, -24, -43,
+    ],
+];
+
+// Material plus placement of each `SidePiece` on each square, from White's view
+static PSQ_TABLES: [[(i32, i32); 64]; 12] = psq_tables();
+
+// Score in centipawns. Mates are encoded as a distance from `MATE`, so shorter
+// mates score higher.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Score(pub i32);
+
+// Middlegame and endgame scores from White's view, along with the game phase
+// used to blend them. Kept up to date as pieces are placed and removed.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct Psq {
+    mg: i32,
+    eg: i32,
+    phase: i32,
+}
+
+const fn psq_tables() -> [[(i32, i32); 64]; 12] {
+    let mut tables = [[(0, 0); 64]; 12];
+    let mut piece_type = 0;
+    while piece_type < 6 {
+        let mut sq = 0;
+        while sq < 64 {
+            // Flip the rank for White, since the tables start at a8
+            let white_sq = sq ^ 56;
+            tables[piece_type][sq] = (
+                MG_VALUES[piece_type] + MG_TABLES[piece_type][white_sq],
+                EG_VALUES[piece_type] + EG_TABLES[piece_type][white_sq],
+            );
+            tables[piece_type + 6][sq] = (
+                -MG_VALUES[piece_type] - MG_TABLES[piece_type][sq],
+                -EG_VALUES[piece_type] - EG_TABLES[piece_type][sq],
+            );
+            sq += 1;
+        }
+        piece_type += 1;
+    }
+    tables
+}
+
+impl Score {
+    pub const ZERO: Self = Self(0);
+    pub const DRAW: Self = Self(0);
+    // Side to move delivers mate right away
+    pub const MATE: Self = Self(32_000);
+    // Bound outside any reachable score
+    pub const INFINITE: Self = Self(32_001);
+    // Mates further away than this many plies aren't told apart from other scores
+    const MAX_MATE_PLY: i32 = 1000;
+
+    // Side to move mates in `ply` half-moves
+    pub fn mate_in(ply: u32) -> Self {
+        Self(Self::MATE.0 - ply as i32)
+    }
+
+    // Side to move gets mated in `ply` half-moves
+    pub fn mated_in(ply: u32) -> Self {
+        Self(-Self::MATE.0 + ply as i32)
+    }
+
+    pub fn is_mate(self) -> bool {
+        self.0.abs() > Self::MATE.0 - Self::MAX_MATE_PLY && self.0.abs() <= Self::MATE.0
+    }
+
+    // Full moves until mate as reported over UCI, negative when getting mated
+    pub fn mate_moves(self) -> Option<i32> {
+        if !self.is_mate() {
+            None
+        } else if self.0 > 0 {
+            Some((Self::MATE.0 - self.0 + 1) / 2)
+        } else {
+            Some(-(Self::MATE.0 + self.0) / 2)
+        }
+    }
+}
+
+impl Neg for Score {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Add for Score {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Score {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+// UCI style, like `cp 35` or `mate -2`
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mate_moves() {
+            Some(moves) => write!(f, "mate {}", moves),
+            None => write!(f, "cp {}", self.0),
+        }
+    }
+}
+
+impl Psq {
+    pub(crate) fn add(&mut self, piece: SidePiece, sq: Square) {
+        let (mg, eg) = PSQ_TABLES[piece as usize][sq as usize];
+        self.mg += mg;
+        self.eg += eg;
+        self.phase += PHASE_WEIGHTS[piece.piece_type() as usize];
+    }
+
+    pub(crate) fn remove(&mut self, piece: SidePiece, sq: Square) {
+        let (mg, eg) = PSQ_TABLES[piece as usize][sq as usize];
+        self.mg -= mg;
+        self.eg -= eg;
+        self.phase -= PHASE_WEIGHTS[piece.piece_type() as usize];
+    }
+
+    // Blend of the middlegame and endgame scores, leaning towards the endgame
+    // as pieces come off. Early promotions can push the phase past its start.
+    fn tapered(self) -> i32 {
+        let phase = self.phase.min(MAX_PHASE);
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+// Static evaluation from the side to move's view
+pub fn evaluate(board: &Board) -> Score {
+    let score = board.psq().tapered();
+    match board.turn {
+        Color::White => Score(score),
+        Color::Black => Score(-score),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoardPiece;
+
+    // Piece-square totals summed over the board from scratch
+    fn compute_psq(board: &Board) -> Psq {
+        let mut psq = Psq::default();
+        for sq in board.occupied() {
+            if let BoardPiece::Piece(piece) = board.piece_at(sq) {
+                psq.add(piece, sq);
+            }
+        }
+        psq
+    }
+
+    fn mirror(fen: &str) -> String {
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let placement: Vec<String> = fields[0]
+            .rsplit('/')
+            .map(|rank| {
+                rank.chars()
+                    .map(|c| {
+                        if c.is_ascii_uppercase() {
+                            c.to_ascii_lowercase()
+                        } else {
+                            c.to_ascii_uppercase()
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let turn = if fields[1] == "w" { "b" } else { "w" };
+        format!("{} {} - - 0 1", placement.join("/"), turn)
+    }
+
+    #[test]
+    fn test_evaluate_symmetry() {
+        let start = Board::default();
+        assert_eq!(evaluate(&start), Score::ZERO);
+        assert_eq!(start.psq().phase, MAX_PHASE);
+
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w - - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w - - 1 8",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            let mirrored = Board::from_fen(&mirror(fen)).unwrap();
+            assert_eq!(evaluate(&board), evaluate(&mirrored), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_material() {
+        // Up a queen is good for White and bad for Black
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(evaluate(&board).0 > 800);
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1").unwrap();
+        assert!(evaluate(&board).0 < -800);
+        assert_eq!(board.psq().phase, 4);
+    }
+
+    #[test]
+    fn test_incremental_psq() {
+        let mut board =
+            Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let start = board.psq();
+        assert_eq!(start, compute_psq(&board));
+        for mv in board.gen_legal_moves() {
+            let state = board.make_move(mv);
+            assert_eq!(board.psq(), compute_psq(&board), "{:?}", mv);
+            for reply in board.gen_legal_moves() {
+                let reply_state = board.make_move(reply);
+                assert_eq!(board.psq(), compute_psq(&board), "{:?}", reply);
+                board.undo_move(reply_state);
+            }
+            board.undo_move(state);
+            assert_eq!(board.psq(), start);
+        }
+    }
+
+    #[test]
+    fn test_mate_scores() {
+        assert!(Score::mate_in(3).is_mate());
+        assert!(Score::mated_in(4).is_mate());
+        assert!(!Score(900).is_mate());
+        assert!(!Score::INFINITE.is_mate());
+        assert!(Score::mate_in(1) > Score::mate_in(3));
+        assert!(Score::mated_in(2) < Score::mated_in(4));
+        assert_eq!(-Score::mate_in(5), Score::mated_in(5));
+        assert_eq!(Score::mate_in(1).mate_moves(), Some(1));
+        assert_eq!(Score::mate_in(3).mate_moves(), Some(2));
+        assert_eq!(Score::mated_in(2).mate_moves(), Some(-1));
+        assert_eq!(Score::mated_in(0).mate_moves(), Some(0));
+        assert_eq!(Score(35).to_string(), "cp 35");
+        assert_eq!(Score::mate_in(3).to_string(), "mate 2");
+        assert_eq!(Score::mated_in(4).to_string(), "mate -2");
+        assert_eq!(Score(10) - Score(25), -Score(15));
+    }
+}
@@ -1,5 +1,5 @@
 use crate::moves::StateChange;
-use crate::{Board, FenError, Move};
+use crate::{Annotation, Board, Color, FenError, Move, MoveError};
 use std::fmt;
 
 const INIT_MOVE_HIST_LEN: usize = 32;
@@ -7,6 +7,31 @@ const INIT_MOVE_HIST_LEN: usize = 32;
 pub struct Game {
     pub board: Board,
     history: Vec<StateChange>,
+    // Comments, NAGs and variations of each move in `history`
+    annotations: Vec<Annotation>,
+    // PGN tag pairs, in the order they were read or set
+    pub tags: Vec<(String, String)>,
+    // Result decided off the board, by resignation, timeout or agreement
+    result: Option<Outcome>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    // `None` for a draw
+    pub winner: Option<Color>,
+    pub reason: OutcomeReason,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OutcomeReason {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    Repetition,
+    FiftyMove,
+    Resignation,
+    Timeout,
+    Agreement,
 }
 
 impl Game {
@@ -14,6 +39,9 @@ impl Game {
         Game {
             board,
             history: Vec::with_capacity(INIT_MOVE_HIST_LEN),
+            annotations: Vec::with_capacity(INIT_MOVE_HIST_LEN),
+            tags: Vec::new(),
+            result: None,
         }
     }
 
@@ -21,10 +49,6 @@ impl Game {
         Self::from_board(Board::empty())
     }
 
-    pub fn default() -> Self {
-        Self::from_board(Board::default())
-    }
-
     pub fn from_fen(fen: &str) -> Result<Self, FenError> {
         Board::from_fen(fen).map(Self::from_board)
     }
@@ -32,13 +56,199 @@ impl Game {
     pub fn make_move(&mut self, mv: Move) {
         let state = self.board.make_move(mv);
         self.history.push(state);
+        self.annotations.push(Annotation::default());
+    }
+
+    // Plays `mv` if it is legal, like `Board::try_make_move`
+    pub fn try_make_move(&mut self, mv: Move) -> Result<(), MoveError> {
+        let state = self.board.try_make_move(mv)?;
+        self.history.push(state);
+        self.annotations.push(Annotation::default());
+        Ok(())
     }
 
+    // Also takes back any result recorded since the move was made
     pub fn undo_move(&mut self) -> Option<StateChange> {
         let state = self.history.pop()?;
+        self.annotations.pop();
         self.board.undo_move(state);
+        self.result = None;
         Some(state)
     }
+
+    // Moves played so far, from the first
+    pub fn moves(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|state| state.last_move)
+    }
+
+    // Hashes of the positions before each move played, from the first
+    pub fn position_hashes(&self) -> Vec<u64> {
+        self.history.iter().map(|state| state.last_hash).collect()
+    }
+
+    // Position before the first move
+    pub fn start_board(&self) -> Board {
+        let mut board = self.board.clone();
+        for &state in self.history.iter().rev() {
+            board.undo_move(state);
+        }
+        board
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    // Annotation of the move at index `ply` of the history
+    pub fn annotation_mut(&mut self, ply: usize) -> Option<&mut Annotation> {
+        self.annotations.get_mut(ply)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Replaces the value of an existing tag, keeping its position
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(tag, _)| tag == name) {
+            Some((_, old)) => *old = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    // How the game ended, or `None` if it is still going. Draws which have to be
+    // claimed only count after a successful `claim_draw`.
+    pub fn outcome(&self) -> Option<Outcome> {
+        if self.result.is_some() {
+            return self.result;
+        }
+        let (winner, reason) = if self.board.gen_legal_moves().is_empty() {
+            if self.board.is_in_check() {
+                (Some(!self.board.turn), OutcomeReason::Checkmate)
+            } else {
+                (None, OutcomeReason::Stalemate)
+            }
+        } else if self.board.is_insufficient_material() {
+            (None, OutcomeReason::InsufficientMaterial)
+        } else if self.is_fivefold_repetition() {
+            (None, OutcomeReason::Repetition)
+        } else if self.is_seventy_five_move_draw() {
+            (None, OutcomeReason::FiftyMove)
+        } else {
+            return None;
+        };
+        Some(Outcome { winner, reason })
+    }
+
+    pub fn resign(&mut self, color: Color) {
+        self.result = Some(Outcome {
+            winner: Some(!color),
+            reason: OutcomeReason::Resignation,
+        });
+    }
+
+    // Flag fall for `color`, which is only a loss if the opponent could still mate
+    pub fn timeout(&mut self, color: Color) {
+        let winner = if self.board.has_insufficient_material(!color) {
+            None
+        } else {
+            Some(!color)
+        };
+        self.result = Some(Outcome {
+            winner,
+            reason: OutcomeReason::Timeout,
+        });
+    }
+
+    pub fn agree_draw(&mut self) {
+        self.result = Some(Outcome {
+            winner: None,
+            reason: OutcomeReason::Agreement,
+        });
+    }
+
+    // Claim a draw by threefold repetition or the fifty-move rule, returning
+    // whether the claim was valid
+    pub fn claim_draw(&mut self) -> bool {
+        let reason = if self.is_threefold_repetition() {
+            OutcomeReason::Repetition
+        } else if self.is_fifty_move_draw() {
+            OutcomeReason::FiftyMove
+        } else {
+            return false;
+        };
+        self.result = Some(Outcome {
+            winner: None,
+            reason,
+        });
+        true
+    }
+
+    // Number of times the current position has occurred, including now.
+    // Only looks back to the last irreversible move, since nothing before it can
+    // repeat.
+    pub fn repetition_count(&self) -> usize {
+        let hash = self.board.hash();
+        let reversible = (self.board.halfmove_clock as usize).min(self.history.len());
+        1 + self.history[self.history.len() - reversible..]
+            .iter()
+            .filter(|state| state.last_hash == hash)
+            .count()
+    }
+
+    // Claimable draw when the same position occurs for the third time
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.repetition_count() >= 3
+    }
+
+    // Automatic draw when the same position occurs for the fifth time
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetition_count() >= 5
+    }
+
+    // Claimable draw after 50 moves by each side without a pawn move or capture,
+    // unless the last move delivered checkmate
+    pub fn is_fifty_move_draw(&self) -> bool {
+        self.board.halfmove_clock >= 100 && !self.is_checkmate()
+    }
+
+    // Automatic draw after 75 moves by each side without a pawn move or capture,
+    // unless the last move delivered checkmate
+    pub fn is_seventy_five_move_draw(&self) -> bool {
+        self.board.halfmove_clock >= 150 && !self.is_checkmate()
+    }
+
+    fn is_checkmate(&self) -> bool {
+        self.board.is_in_check() && self.board.gen_legal_moves().is_empty()
+    }
+
+    pub fn perft(&mut self, depth: u8) -> u64 {
+        self.board.perft(depth)
+    }
+
+    pub fn perft_divide(&mut self, depth: u8) -> Vec<(Move, u64)> {
+        self.board.perft_divide(depth)
+    }
+}
+
+impl Outcome {
+    // Score as written in PGN and game protocols, like `1-0` or `1/2-1/2`
+    pub fn score(&self) -> &'static str {
+        match self.winner {
+            Some(Color::White) => "1-0",
+            Some(Color::Black) => "0-1",
+            None => "1/2-1/2",
+        }
+    }
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::from_board(Board::default())
+    }
 }
 
 impl fmt::Debug for Game {
@@ -52,3 +262,184 @@ impl fmt::Display for Game {
         write!(f, "{}", self.board)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+
+    fn shuffle_knights(game: &mut Game, times: usize) {
+        for _ in 0..times {
+            game.make_move(Move::normal(Square::G1, Square::F3));
+            game.make_move(Move::normal(Square::G8, Square::F6));
+            game.make_move(Move::normal(Square::F3, Square::G1));
+            game.make_move(Move::normal(Square::F6, Square::G8));
+        }
+    }
+
+    #[test]
+    fn test_try_make_move() {
+        let mut game = Game::default();
+        assert_eq!(
+            game.try_make_move(Move::normal(Square::E2, Square::E5)),
+            Err(MoveError::IllegalDestination)
+        );
+        assert_eq!(game.moves().count(), 0);
+        assert!(game
+            .try_make_move(Move::normal(Square::G1, Square::F3))
+            .is_ok());
+        assert_eq!(game.moves().count(), 1);
+        assert_eq!(game.annotations().len(), 1);
+    }
+
+    #[test]
+    fn test_repetition() {
+        let mut game = Game::default();
+        assert_eq!(game.repetition_count(), 1);
+        shuffle_knights(&mut game, 1);
+        assert_eq!(game.repetition_count(), 2);
+        assert!(!game.is_threefold_repetition());
+        shuffle_knights(&mut game, 1);
+        assert!(game.is_threefold_repetition());
+        assert!(!game.is_fivefold_repetition());
+        shuffle_knights(&mut game, 2);
+        assert!(game.is_fivefold_repetition());
+        game.undo_move();
+        assert_eq!(game.repetition_count(), 4);
+    }
+
+    #[test]
+    fn test_repetition_after_irreversible_move() {
+        let mut game = Game::default();
+        shuffle_knights(&mut game, 1);
+        game.make_move(Move::normal(Square::B1, Square::C3));
+        game.make_move(Move::normal(Square::E7, Square::E6));
+        shuffle_knights(&mut game, 1);
+        assert_eq!(game.repetition_count(), 2);
+    }
+
+    fn draw(reason: OutcomeReason) -> Option<Outcome> {
+        Some(Outcome {
+            winner: None,
+            reason,
+        })
+    }
+
+    #[test]
+    fn test_outcome_on_board() {
+        assert_eq!(Game::default().outcome(), None);
+        let game = Game::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome {
+                winner: Some(Color::White),
+                reason: OutcomeReason::Checkmate
+            })
+        );
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), draw(OutcomeReason::Stalemate));
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/2B1K3 b - - 0 1").unwrap();
+        assert_eq!(game.outcome(), draw(OutcomeReason::InsufficientMaterial));
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 150 100").unwrap();
+        assert_eq!(game.outcome(), draw(OutcomeReason::FiftyMove));
+
+        let mut game = Game::default();
+        shuffle_knights(&mut game, 2);
+        assert_eq!(game.outcome(), None);
+        shuffle_knights(&mut game, 2);
+        assert_eq!(game.outcome(), draw(OutcomeReason::Repetition));
+    }
+
+    #[test]
+    fn test_outcome_score() {
+        let mut game = Game::default();
+        game.resign(Color::Black);
+        assert_eq!(game.outcome().unwrap().score(), "1-0");
+        game.resign(Color::White);
+        assert_eq!(game.outcome().unwrap().score(), "0-1");
+        game.agree_draw();
+        assert_eq!(game.outcome().unwrap().score(), "1/2-1/2");
+    }
+
+    #[test]
+    fn test_outcome_off_board() {
+        let mut game = Game::default();
+        game.resign(Color::White);
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome {
+                winner: Some(Color::Black),
+                reason: OutcomeReason::Resignation
+            })
+        );
+
+        let mut game = Game::default();
+        game.make_move(Move::normal(Square::G1, Square::F3));
+        game.agree_draw();
+        assert_eq!(game.outcome(), draw(OutcomeReason::Agreement));
+        game.undo_move();
+        assert_eq!(game.outcome(), None);
+
+        let mut game = Game::default();
+        game.timeout(Color::Black);
+        assert_eq!(
+            game.outcome(),
+            Some(Outcome {
+                winner: Some(Color::White),
+                reason: OutcomeReason::Timeout
+            })
+        );
+        // Lone king can't win on time
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        game.timeout(Color::White);
+        assert_eq!(game.outcome(), draw(OutcomeReason::Timeout));
+        // Helpmates count, like Nf7# with Kh8 boxed in by its own queen, or
+        // Bd5# against Ka8 boxed in by its rook
+        for &fen in [
+            "6qk/8/7K/4N3/8/8/8/8 b - - 0 1",
+            "kr6/5B2/1K6/8/8/8/8/8 b - - 0 1",
+        ]
+        .iter()
+        {
+            let mut game = Game::from_fen(fen).unwrap();
+            game.timeout(Color::Black);
+            assert_eq!(
+                game.outcome(),
+                Some(Outcome {
+                    winner: Some(Color::White),
+                    reason: OutcomeReason::Timeout
+                }),
+                "{}",
+                fen
+            );
+        }
+    }
+
+    #[test]
+    fn test_claim_draw() {
+        let mut game = Game::default();
+        assert!(!game.claim_draw());
+        shuffle_knights(&mut game, 2);
+        assert_eq!(game.outcome(), None);
+        assert!(game.claim_draw());
+        assert_eq!(game.outcome(), draw(OutcomeReason::Repetition));
+    }
+
+    #[test]
+    fn test_fifty_move_draw() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert!(!game.is_fifty_move_draw());
+        game.make_move(Move::normal(Square::A1, Square::A2));
+        assert!(game.is_fifty_move_draw());
+        assert!(!game.is_seventy_five_move_draw());
+        game.undo_move();
+        assert!(!game.is_fifty_move_draw());
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 150 100").unwrap();
+        assert!(game.is_seventy_five_move_draw());
+        // Checkmate on the last move takes precedence
+        let game = Game::from_fen("R3k3/8/4K3/8/8/8/8/8 b - - 150 100").unwrap();
+        assert!(!game.is_fifty_move_draw());
+        assert!(!game.is_seventy_five_move_draw());
+    }
+}
@@ -1,11 +1,36 @@
+mod attacks;
+mod bitboard;
 mod board;
+mod engine;
+mod epd;
+mod eval;
 mod game;
+mod magic;
+mod movepick;
 mod moves;
+mod pgn;
 mod piece;
 mod position;
+mod san;
+mod search;
+mod see;
+mod tt;
+mod uci;
+mod validate;
+mod zobrist;
 
-pub use board::{Board, FenError};
-pub use game::Game;
-pub use moves::{CastlingRights, Move, MoveType};
+pub use bitboard::{Bitboard, BitboardIter};
+pub use board::{Board, FenError, FenField, FenReason, MoveError};
+pub use engine::Engine;
+pub use epd::{Epd, EpdError};
+pub use eval::{evaluate, Score};
+pub use game::{Game, Outcome, OutcomeReason};
+pub use moves::{CastlingRights, Move, MoveType, StateChange};
+pub use pgn::{Annotation, PgnError, PgnMove, PgnReader};
 pub use piece::{BoardPiece, Color, PieceType, SidePiece};
 pub use position::{File, Rank, Square};
+pub use san::SanError;
+pub use search::{Search, SearchInfo, SearchLimits, MAX_PLY};
+pub use tt::{Bound, TranspositionTable, TtEntry};
+pub use uci::UciMoveError;
+pub use validate::PositionIssue;
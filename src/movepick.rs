@@ -0,0 +1,438 @@
+use crate::eval::PIECE_VALUES;
+use crate::{Board, BoardPiece, Color, Move, MoveType, PieceType, MAX_PLY};
+
+// History scores are kept within this, so recent cutoffs can outweigh old ones
+const HISTORY_MAX: i32 = 16384;
+
+// Move ordering statistics gathered over a search from beta cutoffs by quiet
+// moves, which the picker uses to try likely refutations first
+pub(crate) struct Heuristics {
+    // Two quiet moves per ply which caused a cutoff in a sibling node
+    killers: Vec<[Option<Move>; 2]>,
+    // Quiet refutation of the last move, by its `SidePiece` and target square
+    counters: [[Option<Move>; 64]; 12],
+    // Cutoff score of each quiet move, by `Color`, from and to square
+    history: Box<[[[i32; 64]; 64]; 2]>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+    TtMove,
+    GenCaptures,
+    GoodCaptures,
+    Promotions,
+    Killers,
+    Counter,
+    GenQuiets,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+// Hands out the legal moves of a position one at a time, best guesses first:
+// the transposition table move, captures winning material by MVV-LVA, quiet
+// promotions, killers, the counter-move, other quiet moves by history score
+// and finally captures losing material by SEE. Each kind of move is only
+// generated once the ones before run out, so a cutoff on a capture never pays
+// for generating quiet moves.
+pub(crate) struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    counter: Option<Move>,
+    // Only captures and promotions, for quiescence
+    captures_only: bool,
+    // Whether promotions to rook and bishop are handed out at all
+    under_promotions: bool,
+    // Scored moves of the current stage, taken best first
+    scored: Vec<(Move, i32)>,
+    promotions: Vec<Move>,
+    bad_captures: Vec<Move>,
+    // Position in `killers`, `promotions` or `bad_captures`
+    index: usize,
+}
+
+// Captures, en-passant and promotions
+fn is_tactical(board: &Board, mv: Move) -> bool {
+    board.piece_at(mv.to()) != BoardPiece::Empty
+        || matches!(mv.move_type(), MoveType::EnPassant | MoveType::Promotion(_))
+}
+
+// Most valuable victim first, then least valuable attacker
+fn mvv_lva(board: &Board, mv: Move) -> i32 {
+    let victim = match (board.piece_at(mv.to()), mv.move_type()) {
+        (BoardPiece::Piece(piece), _) => PIECE_VALUES[piece.piece_type() as usize],
+        (_, MoveType::EnPassant) => PIECE_VALUES[0],
+        _ => 0,
+    };
+    let promotion = match mv.move_type() {
+        MoveType::Promotion(promo) => PIECE_VALUES[promo as usize],
+        _ => 0,
+    };
+    let attacker = match board.piece_at(mv.from()) {
+        BoardPiece::Piece(piece) => PIECE_VALUES[piece.piece_type() as usize],
+        BoardPiece::Empty => 0,
+    };
+    victim + promotion - attacker / 100
+}
+
+// Removes and returns the highest scored move
+fn take_best(scored: &mut Vec<(Move, i32)>) -> Option<Move> {
+    let best = (0..scored.len()).max_by_key(|&i| scored[i].1)?;
+    Some(scored.swap_remove(best).0)
+}
+
+impl Heuristics {
+    pub(crate) fn new() -> Self {
+        Heuristics {
+            killers: vec![[None; 2]; MAX_PLY + 1],
+            counters: [[None; 64]; 12],
+            history: Box::new([[[0; 64]; 64]; 2]),
+        }
+    }
+
+    pub(crate) fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers[ply]
+    }
+
+    // Counter-move to `prev`, the move which led to `board`
+    pub(crate) fn counter(&self, board: &Board, prev: Move) -> Option<Move> {
+        match board.piece_at(prev.to()) {
+            BoardPiece::Piece(piece) => self.counters[piece as usize][prev.to() as usize],
+            BoardPiece::Empty => None,
+        }
+    }
+
+    fn history(&self, color: Color, mv: Move) -> i32 {
+        self.history[color as usize][mv.from() as usize][mv.to() as usize]
+    }
+
+    // Records that quiet move `mv` caused a beta cutoff on `board`, after
+    // the quiet moves in `tried` failed to
+    pub(crate) fn record_cutoff(
+        &mut self,
+        board: &Board,
+        mv: Move,
+        ply: usize,
+        prev: Option<Move>,
+        depth: u8,
+        tried: &[Move],
+    ) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+        if let Some(prev) = prev {
+            if let BoardPiece::Piece(piece) = board.piece_at(prev.to()) {
+                self.counters[piece as usize][prev.to() as usize] = Some(mv);
+            }
+        }
+        let bonus = i32::from(depth) * i32::from(depth);
+        self.update_history(board.turn, mv, bonus);
+        for &quiet in tried {
+            self.update_history(board.turn, quiet, -bonus);
+        }
+    }
+
+    // Moves the score towards the bound in the direction of `bonus`, by less
+    // the closer it already is
+    fn update_history(&mut self, color: Color, mv: Move, bonus: i32) {
+        let score = &mut self.history[color as usize][mv.from() as usize][mv.to() as usize];
+        *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+    }
+}
+
+impl MovePicker {
+    // Picker for every legal move
+    pub(crate) fn new(
+        tt_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        counter: Option<Move>,
+    ) -> Self {
+        MovePicker {
+            stage: Stage::TtMove,
+            tt_move,
+            killers,
+            counter,
+            captures_only: false,
+            under_promotions: true,
+            scored: Vec::new(),
+            promotions: Vec::new(),
+            bad_captures: Vec::new(),
+            index: 0,
+        }
+    }
+
+    // Picker for captures and promotions only
+    pub(crate) fn captures() -> Self {
+        MovePicker {
+            captures_only: true,
+            ..Self::new(None, [None; 2], None)
+        }
+    }
+
+    // Leaves out promotions to rook and bishop unless `enabled`. They are
+    // almost never better than a queen, while knights can give checks a queen
+    // can't, so knights are kept.
+    pub(crate) fn under_promotions(self, enabled: bool) -> Self {
+        MovePicker {
+            under_promotions: enabled,
+            ..self
+        }
+    }
+
+    // Whether `mv` is an under-promotion left out by `under_promotions`
+    fn is_pruned(&self, mv: Move) -> bool {
+        !self.under_promotions
+            && matches!(
+                mv.move_type(),
+                MoveType::Promotion(PieceType::Rook) | MoveType::Promotion(PieceType::Bishop)
+            )
+    }
+
+    // Whether `mv` was already handed out before the quiet moves
+    fn is_special(&self, mv: Move) -> bool {
+        Some(mv) == self.tt_move || self.killers.contains(&Some(mv)) || Some(mv) == self.counter
+    }
+
+    pub(crate) fn next(&mut self, board: &Board, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenCaptures;
+                    match self.tt_move {
+                        Some(mv) if !self.is_pruned(mv) && board.is_legal(mv) => return Some(mv),
+                        _ => self.tt_move = None,
+                    }
+                }
+                Stage::GenCaptures => {
+                    for mv in board.gen_captures() {
+                        if Some(mv) == self.tt_move || self.is_pruned(mv) {
+                            continue;
+                        }
+                        if board.piece_at(mv.to()) == BoardPiece::Empty
+                            && mv.move_type() != MoveType::EnPassant
+                        {
+                            self.promotions.push(mv);
+                        } else if board.see_ge(mv, 0) {
+                            self.scored.push((mv, mvv_lva(board, mv)));
+                        } else {
+                            self.bad_captures.push(mv);
+                        }
+                    }
+                    self.promotions.sort_by_key(|&mv| -mvv_lva(board, mv));
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match take_best(&mut self.scored) {
+                    Some(mv) => return Some(mv),
+                    None => {
+                        self.index = 0;
+                        self.stage = Stage::Promotions;
+                    }
+                },
+                Stage::Promotions => match self.promotions.get(self.index) {
+                    Some(&mv) => {
+                        self.index += 1;
+                        return Some(mv);
+                    }
+                    None => {
+                        self.index = 0;
+                        self.stage = if self.captures_only {
+                            Stage::BadCaptures
+                        } else {
+                            Stage::Killers
+                        };
+                    }
+                },
+                Stage::Killers => match self.killers.get(self.index) {
+                    Some(&killer) => {
+                        self.index += 1;
+                        match killer {
+                            Some(mv)
+                                if Some(mv) != self.tt_move
+                                    && !is_tactical(board, mv)
+                                    && board.is_legal(mv) =>
+                            {
+                                return Some(mv)
+                            }
+                            _ => {}
+                        }
+                    }
+                    None => self.stage = Stage::Counter,
+                },
+                Stage::Counter => {
+                    self.stage = Stage::GenQuiets;
+                    match self.counter {
+                        Some(mv)
+                            if Some(mv) != self.tt_move
+                                && !self.killers.contains(&Some(mv))
+                                && !is_tactical(board, mv)
+                                && board.is_legal(mv) =>
+                        {
+                            return Some(mv)
+                        }
+                        _ => {}
+                    }
+                }
+                Stage::GenQuiets => {
+                    for mv in board.gen_quiets() {
+                        if !self.is_special(mv) {
+                            self.scored.push((mv, heuristics.history(board.turn, mv)));
+                        }
+                    }
+                    self.stage = Stage::Quiets;
+                }
+                Stage::Quiets => match take_best(&mut self.scored) {
+                    Some(mv) => return Some(mv),
+                    None => {
+                        self.index = 0;
+                        self.stage = Stage::BadCaptures;
+                    }
+                },
+                Stage::BadCaptures => match self.bad_captures.get(self.index) {
+                    Some(&mv) => {
+                        self.index += 1;
+                        return Some(mv);
+                    }
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+    use Square::*;
+
+    fn pick_all(board: &Board, mut picker: MovePicker, heuristics: &Heuristics) -> Vec<Move> {
+        let mut moves = Vec::new();
+        while let Some(mv) = picker.next(board, heuristics) {
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_picks_every_move_once() {
+        let heuristics = Heuristics::new();
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ]
+        .iter()
+        {
+            let board = Board::from_fen(fen).unwrap();
+            let legal = board.gen_legal_moves();
+            // Special moves which aren't legal here are skipped
+            let killers = [Some(legal[3]), Some(Move::normal(A3, A4))];
+            let picker = MovePicker::new(Some(legal[5]), killers, Some(legal[3]));
+            let picked = pick_all(&board, picker, &heuristics);
+            assert_eq!(picked[0], legal[5]);
+            assert_eq!(picked.len(), legal.len(), "{}", fen);
+            assert!(legal.iter().all(|mv| picked.contains(mv)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_stage_order() {
+        // Pawn takes a hanging knight, queen takes a defended pawn, and quiet
+        // promotions
+        let board = Board::from_fen("4k3/P7/3p4/4p3/3n4/2P5/8/4QK2 w - - 0 1").unwrap();
+        let mut heuristics = Heuristics::new();
+        let killer = Move::normal(F1, G1);
+        let good_quiet = Move::normal(E1, H4);
+        heuristics.record_cutoff(&board, good_quiet, 1, None, 5, &[]);
+        let mut picker = MovePicker::new(None, [Some(killer), None], None);
+
+        assert_eq!(picker.next(&board, &heuristics), Some(Move::normal(C3, D4)));
+        let queen = Move::new(A7, A8, MoveType::Promotion(PieceType::Queen));
+        assert_eq!(picker.next(&board, &heuristics), Some(queen));
+        // Quiets aren't generated while there are other moves to try
+        assert!(picker.scored.is_empty());
+        assert_ne!(picker.stage, Stage::Quiets);
+
+        let rest = pick_all(&board, picker, &heuristics);
+        let killer_at = rest.iter().position(|&mv| mv == killer).unwrap();
+        let good_quiet_at = rest.iter().position(|&mv| mv == good_quiet).unwrap();
+        assert!(killer_at < good_quiet_at);
+        // The losing capture comes last
+        assert_eq!(rest.last(), Some(&Move::normal(E1, E5)));
+    }
+
+    #[test]
+    fn test_captures_only() {
+        let board = Board::from_fen("4k3/P7/3p4/4p3/3n4/2P5/8/4QK2 w - - 0 1").unwrap();
+        let picked = pick_all(&board, MovePicker::captures(), &Heuristics::new());
+        let mut expected = board.gen_captures();
+        assert_eq!(picked.len(), expected.len());
+        expected.retain(|mv| !picked.contains(mv));
+        assert!(expected.is_empty());
+    }
+
+    #[test]
+    fn test_skip_under_promotions() {
+        let board = Board::from_fen("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let is_under = |mv: &Move| {
+            matches!(
+                mv.move_type(),
+                MoveType::Promotion(PieceType::Rook) | MoveType::Promotion(PieceType::Bishop)
+            )
+        };
+        let all = pick_all(
+            &board,
+            MovePicker::new(None, [None; 2], None),
+            &Heuristics::new(),
+        );
+        assert_eq!(all.iter().filter(|mv| is_under(mv)).count(), 4);
+
+        let picker = MovePicker::new(None, [None; 2], None).under_promotions(false);
+        let pruned = pick_all(&board, picker, &Heuristics::new());
+        assert_eq!(pruned.len(), all.len() - 4);
+        assert!(!pruned.iter().any(is_under));
+        let knights = MoveType::Promotion(PieceType::Knight);
+        assert_eq!(
+            pruned.iter().filter(|mv| mv.move_type() == knights).count(),
+            2
+        );
+
+        // Even from the transposition table
+        let rook = Move::new(A7, A8, MoveType::Promotion(PieceType::Rook));
+        let picker = MovePicker::new(Some(rook), [None; 2], None).under_promotions(false);
+        let pruned_tt = pick_all(&board, picker, &Heuristics::new());
+        assert_eq!(pruned_tt.len(), pruned.len());
+        assert!(!pruned_tt.contains(&rook));
+    }
+
+    #[test]
+    fn test_heuristics() {
+        let board = Board::default();
+        let mut heuristics = Heuristics::new();
+        let first = Move::normal(G1, F3);
+        let second = Move::normal(B1, C3);
+        heuristics.record_cutoff(&board, first, 2, None, 3, &[]);
+        heuristics.record_cutoff(&board, second, 2, None, 3, &[first]);
+        assert_eq!(heuristics.killers(2), [Some(second), Some(first)]);
+        assert_eq!(heuristics.killers(3), [None, None]);
+        assert!(heuristics.history(Color::White, second) > 0);
+        assert_eq!(heuristics.history(Color::White, first), 0);
+        for _ in 0..1000 {
+            heuristics.record_cutoff(&board, second, 2, None, 60, &[]);
+        }
+        assert!(heuristics.history(Color::White, second) <= HISTORY_MAX);
+
+        // Counter-moves are keyed by the piece that moved and where it went
+        let mut board = Board::default();
+        let prev = Move::new(E2, E4, MoveType::DoublePush);
+        board.make_move(prev);
+        let reply = Move::normal(G8, F6);
+        heuristics.record_cutoff(&board, reply, 1, Some(prev), 2, &[]);
+        assert_eq!(heuristics.counter(&board, prev), Some(reply));
+    }
+}
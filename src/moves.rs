@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 use std::fmt;
 use std::str::FromStr;
 
-use crate::{BoardPiece, File, PieceType, Square};
+use crate::{BoardPiece, Color, File, PieceType, Square};
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum MoveType {
@@ -29,6 +29,8 @@ pub struct StateChange {
     pub captured: BoardPiece,
     pub last_ep_file: Option<File>,
     pub last_castle_rights: CastlingRights,
+    pub last_halfmove_clock: u8,
+    pub last_hash: u64,
 }
 
 impl Move {
@@ -56,12 +58,46 @@ impl Move {
     pub fn move_type(self) -> MoveType {
         self.move_type
     }
+
+    // Squares in the low 12 bits and the move type in the top 4, so a move fits
+    // in a transposition table entry. Never zero, since a move can't stay put.
+    pub fn pack(self) -> u16 {
+        let move_type = match self.move_type {
+            MoveType::Normal => 0,
+            MoveType::EnPassant => 1,
+            MoveType::DoublePush => 2,
+            MoveType::Castle => 3,
+            MoveType::Promotion(promo) => 4 + promo as u16,
+        };
+        self.from as u16 | (self.to as u16) << 6 | move_type << 12
+    }
+
+    // Inverse of `pack`, or `None` for bits no move packs to
+    pub fn unpack(bits: u16) -> Option<Self> {
+        let from = Square::try_from((bits & 0x3f) as u8).ok()?;
+        let to = Square::try_from((bits >> 6 & 0x3f) as u8).ok()?;
+        let move_type = match bits >> 12 {
+            0 => MoveType::Normal,
+            1 => MoveType::EnPassant,
+            2 => MoveType::DoublePush,
+            3 => MoveType::Castle,
+            5 => MoveType::Promotion(PieceType::Rook),
+            6 => MoveType::Promotion(PieceType::Knight),
+            7 => MoveType::Promotion(PieceType::Bishop),
+            8 => MoveType::Promotion(PieceType::Queen),
+            _ => return None,
+        };
+        if from == to {
+            return None;
+        }
+        Some(Self::new(from, to, move_type))
+    }
 }
 
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         // Chess algebraic notation for moves is annoying.
-        // Also, it requires info not contained in `Move`.
+        // Also, it requires info not contained in `Move`, see `Board::move_to_san`.
         write!(f, "{}->{}", self.from(), self.to())
     }
 }
@@ -70,7 +106,7 @@ impl FromStr for Move {
     type Err = ();
 
     // NOTE: Since this doesn't have access to a `Board`, it can only disambiguate
-    // between `Normal` and `Promotion` moves.
+    // between `Normal` and `Promotion` moves, see `Board::parse_uci_move`.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if !matches!(s.len(), 4 | 5) {
             return Err(());
@@ -118,6 +154,60 @@ impl CastlingRights {
     pub fn black_queen(self) -> bool {
         self.0 & 0b0001 != 0
     }
+
+    // Rights as the files of the castling rooks, like `HAha`, as in Shredder-FEN
+    pub fn to_shredder(self) -> String {
+        let shredder: String = [
+            (self.white_king(), 'H'),
+            (self.white_queen(), 'A'),
+            (self.black_king(), 'h'),
+            (self.black_queen(), 'a'),
+        ]
+        .iter()
+        .filter(|&&(right, _)| right)
+        .map(|&(_, letter)| letter)
+        .collect();
+        if shredder.is_empty() {
+            "-".to_string()
+        } else {
+            shredder
+        }
+    }
+
+    // Distinct index in 0..16 for each combination of rights
+    pub(crate) fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    pub fn king_side(self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_king(),
+            Color::Black => self.black_king(),
+        }
+    }
+
+    pub fn queen_side(self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queen(),
+            Color::Black => self.black_queen(),
+        }
+    }
+
+    // Remove any rights tied to a king or rook starting on `sq`.
+    // Called for both ends of every move, which covers king moves, rook moves,
+    // and rooks being captured.
+    pub fn remove_square(&mut self, sq: Square) {
+        let mask = match sq {
+            Square::E1 => 0b1100,
+            Square::H1 => 0b1000,
+            Square::A1 => 0b0100,
+            Square::E8 => 0b0011,
+            Square::H8 => 0b0010,
+            Square::A8 => 0b0001,
+            _ => return,
+        };
+        self.0 &= !mask;
+    }
 }
 
 impl FromStr for CastlingRights {
@@ -178,6 +268,27 @@ impl fmt::Display for CastlingRights {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_move_pack() {
+        use Square::*;
+        let moves = [
+            Move::normal(G1, F3),
+            Move::new(E5, D6, MoveType::EnPassant),
+            Move::new(E7, E5, MoveType::DoublePush),
+            Move::new(E8, C8, MoveType::Castle),
+            Move::new(B7, A8, MoveType::Promotion(PieceType::Queen)),
+            Move::new(H2, H1, MoveType::Promotion(PieceType::Knight)),
+            Move::new(A7, A8, MoveType::Promotion(PieceType::Rook)),
+            Move::new(C2, B1, MoveType::Promotion(PieceType::Bishop)),
+        ];
+        for &mv in moves.iter() {
+            assert_ne!(mv.pack(), 0);
+            assert_eq!(Move::unpack(mv.pack()), Some(mv));
+        }
+        assert_eq!(Move::unpack(0), None);
+        assert_eq!(Move::unpack(Move::normal(A1, B1).pack() | 15 << 12), None);
+    }
+
     #[test]
     fn test_castling_rights() {
         for &(a, b, c, d) in [
@@ -195,6 +306,23 @@ mod tests {
             assert_eq!(rights.white_queen(), b);
             assert_eq!(rights.black_king(), c);
             assert_eq!(rights.black_queen(), d);
+            assert_eq!(rights.king_side(Color::White), a);
+            assert_eq!(rights.queen_side(Color::White), b);
+            assert_eq!(rights.king_side(Color::Black), c);
+            assert_eq!(rights.queen_side(Color::Black), d);
         }
     }
+
+    #[test]
+    fn test_castling_rights_remove_square() {
+        let mut rights = CastlingRights::all();
+        rights.remove_square(Square::D4);
+        assert_eq!(rights, CastlingRights::all());
+        rights.remove_square(Square::H1);
+        assert_eq!(rights, CastlingRights::new(false, true, true, true));
+        rights.remove_square(Square::E8);
+        assert_eq!(rights, CastlingRights::new(false, true, false, false));
+        rights.remove_square(Square::A1);
+        assert_eq!(rights, CastlingRights::none());
+    }
 }
@@ -0,0 +1,713 @@
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::{Board, Color, FenError, Game, Move, SanError};
+
+// Tags every exported game starts with, in order, with their unknown values
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// Movetext lines are wrapped to fit within this many columns
+const MAX_LINE_LEN: usize = 79;
+
+// Commentary attached to a single move
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Annotation {
+    // Comment appearing before the move, only kept for the first move of a line
+    pub comment_before: Option<String>,
+    pub comment: Option<String>,
+    // Numeric Annotation Glyphs, like 1 for `!` or 4 for `??`
+    pub nags: Vec<u8>,
+    // Alternatives to the move, each starting from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+// Move of a variation, with its own annotation and nested variations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PgnMove {
+    pub mv: Move,
+    pub annotation: Annotation,
+}
+
+#[derive(Clone, Debug)]
+pub enum PgnError {
+    Io(io::ErrorKind),
+    InvalidTag,
+    InvalidFen(FenError),
+    InvalidMove {
+        move_number: u16,
+        san: String,
+        reason: SanError,
+    },
+    UnexpectedToken(String),
+    UnterminatedComment,
+    UnbalancedVariation,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    VariationStart,
+    VariationEnd,
+    Result(String),
+    San(String),
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+struct Parser<'a> {
+    lexer: Lexer<'a>,
+    peeked: Option<Token>,
+    // Game termination marker, once the main line has been read
+    result: Option<String>,
+}
+
+// Reads games one at a time from a PGN file of any size
+pub struct PgnReader<R> {
+    reader: R,
+    // First line of the next game, read while looking for the end of the last
+    pending: String,
+}
+
+impl Game {
+    // Reads the first game of a PGN string. Tags are kept as given, including the
+    // `Result` tag, which is also set from the termination marker.
+    pub fn from_pgn(pgn: &str) -> Result<Self, PgnError> {
+        let mut parser = Parser::new(pgn);
+        let mut tags = Vec::new();
+        while let Some(Token::Tag(_, _)) = parser.peek()? {
+            if let Some(Token::Tag(name, value)) = parser.next()? {
+                tags.push((name, value));
+            }
+        }
+
+        let board = match tags.iter().find(|(name, _)| name == "FEN") {
+            Some((_, fen)) => Board::from_fen(fen).map_err(PgnError::InvalidFen)?,
+            None => Board::default(),
+        };
+        let line = parser.parse_line(&mut board.clone(), false)?;
+
+        let mut game = Game::from_board(board);
+        game.tags = tags;
+        for (ply, pgn_move) in line.into_iter().enumerate() {
+            game.make_move(pgn_move.mv);
+            if let Some(annotation) = game.annotation_mut(ply) {
+                *annotation = pgn_move.annotation;
+            }
+        }
+        if let Some(result) = parser.result {
+            game.set_tag("Result", &result);
+        }
+        Ok(game)
+    }
+
+    // Exports the game with the Seven Tag Roster first, filling in unknown values,
+    // followed by `SetUp` and `FEN` for a non-standard start and then any other tags
+    pub fn to_pgn(&self) -> String {
+        let start = self.start_board();
+        let result = self.pgn_result();
+        let mut pgn = String::new();
+
+        for &(name, unknown) in SEVEN_TAG_ROSTER.iter() {
+            let value = match name {
+                "Result" => result,
+                _ => self.tag(name).unwrap_or(unknown),
+            };
+            push_tag(&mut pgn, name, value);
+        }
+        let fen = start.to_fen();
+        if fen != Board::default().to_fen() {
+            push_tag(&mut pgn, "SetUp", "1");
+            push_tag(&mut pgn, "FEN", &fen);
+        }
+        for (name, value) in self.tags.iter() {
+            let is_roster = SEVEN_TAG_ROSTER.iter().any(|&(tag, _)| tag == name);
+            if !is_roster && name != "SetUp" && name != "FEN" {
+                push_tag(&mut pgn, name, value);
+            }
+        }
+        pgn.push('\n');
+
+        let mut tokens = Vec::new();
+        let line: Vec<_> = self.moves().zip(self.annotations().iter()).collect();
+        write_line(&mut start.clone(), &line, &mut tokens);
+        tokens.push(result.to_string());
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > MAX_LINE_LEN {
+                pgn.push('\n');
+                line_len = 0;
+            } else if line_len > 0 {
+                pgn.push(' ');
+                line_len += 1;
+            }
+            line_len += token.len();
+            pgn.push_str(&token);
+        }
+        pgn.push('\n');
+        pgn
+    }
+
+    // Termination marker, from the board if the game is over and otherwise
+    // from the `Result` tag
+    fn pgn_result(&self) -> &str {
+        match self.outcome() {
+            Some(outcome) => outcome.score(),
+            None => self.tag("Result").unwrap_or("*"),
+        }
+    }
+}
+
+fn push_tag(pgn: &mut String, name: &str, value: &str) {
+    let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+    pgn.push_str(&format!("[{} \"{}\"]\n", name, value));
+}
+
+// Comments can't contain their closing brace, so it is dropped. Split into
+// words so long comments still wrap.
+fn push_comment(tokens: &mut Vec<String>, comment: &str) {
+    let comment = format!("{{{}}}", comment.replace('}', ""));
+    tokens.extend(comment.split_whitespace().map(String::from));
+}
+
+// Movetext tokens for a line starting from `board`, which is left unchanged
+fn write_line(board: &mut Board, line: &[(Move, &Annotation)], tokens: &mut Vec<String>) {
+    let mut states = Vec::new();
+    let mut needs_number = true;
+    for &(mv, annotation) in line {
+        if let Some(comment) = &annotation.comment_before {
+            push_comment(tokens, comment);
+        }
+        if board.turn == Color::White {
+            tokens.push(format!("{}.", board.fullmove_count));
+        } else if needs_number {
+            tokens.push(format!("{}...", board.fullmove_count));
+        }
+        tokens.push(board.move_to_san(mv));
+        for nag in annotation.nags.iter() {
+            tokens.push(format!("${}", nag));
+        }
+        needs_number = false;
+        if let Some(comment) = &annotation.comment {
+            push_comment(tokens, comment);
+            needs_number = true;
+        }
+        for variation in annotation.variations.iter() {
+            let start = tokens.len();
+            let line: Vec<_> = variation
+                .iter()
+                .map(|pgn_move| (pgn_move.mv, &pgn_move.annotation))
+                .collect();
+            write_line(board, &line, tokens);
+            if tokens.len() > start {
+                tokens[start].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+                needs_number = true;
+            }
+        }
+        states.push(board.make_move(mv));
+    }
+    for state in states.into_iter().rev() {
+        board.undo_move(state);
+    }
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str) -> Self {
+        Lexer { src, pos: 0 }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.src[self.pos..]
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.pos == 0 || self.src[..self.pos].ends_with('\n')
+    }
+
+    // Advances past the longest prefix of matching characters and returns it
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> &'a str {
+        let rest = self.rest();
+        let len = rest.find(|c| !pred(c)).unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, PgnError> {
+        loop {
+            let c = match self.rest().chars().next() {
+                Some(c) => c,
+                None => return Ok(None),
+            };
+            // Escaped lines are for other programs to interpret
+            if c == '%' && self.at_line_start() {
+                self.take_while(|c| c != '\n');
+                continue;
+            }
+            // Periods only appear in move numbers, which are ignored
+            if c.is_whitespace() || c == '.' {
+                self.pos += c.len_utf8();
+                continue;
+            }
+            let token = match c {
+                '[' => self.tag()?,
+                '{' => {
+                    let end = self.rest().find('}').ok_or(PgnError::UnterminatedComment)?;
+                    let comment = collapse_whitespace(&self.rest()[1..end]);
+                    self.pos += end + 1;
+                    Token::Comment(comment)
+                }
+                ';' => {
+                    let comment = collapse_whitespace(&self.take_while(|c| c != '\n')[1..]);
+                    Token::Comment(comment)
+                }
+                '(' | ')' => {
+                    self.pos += 1;
+                    if c == '(' {
+                        Token::VariationStart
+                    } else {
+                        Token::VariationEnd
+                    }
+                }
+                '*' => {
+                    self.pos += 1;
+                    Token::Result("*".to_string())
+                }
+                '$' => {
+                    self.pos += 1;
+                    let digits = self.take_while(|c| c.is_ascii_digit());
+                    let nag = digits
+                        .parse()
+                        .map_err(|_| PgnError::UnexpectedToken(format!("${}", digits)))?;
+                    Token::Nag(nag)
+                }
+                '!' | '?' => {
+                    let suffix = self.take_while(|c| c == '!' || c == '?');
+                    let nag = match suffix {
+                        "!" => 1,
+                        "?" => 2,
+                        "!!" => 3,
+                        "??" => 4,
+                        "!?" => 5,
+                        "?!" => 6,
+                        _ => return Err(PgnError::UnexpectedToken(suffix.to_string())),
+                    };
+                    Token::Nag(nag)
+                }
+                c if c.is_ascii_alphanumeric() => {
+                    let symbol =
+                        self.take_while(|c| c.is_ascii_alphanumeric() || "_+#=:-/".contains(c));
+                    match symbol {
+                        "1-0" | "0-1" | "1/2-1/2" => Token::Result(symbol.to_string()),
+                        _ if symbol.chars().all(|c| c.is_ascii_digit()) => continue,
+                        _ => Token::San(symbol.to_string()),
+                    }
+                }
+                _ => return Err(PgnError::UnexpectedToken(c.to_string())),
+            };
+            return Ok(Some(token));
+        }
+    }
+
+    // Tag pair like `[Event "F/S Return Match"]`, with `\"` and `\\` escapes
+    fn tag(&mut self) -> Result<Token, PgnError> {
+        self.pos += 1;
+        self.take_while(char::is_whitespace);
+        let name = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+        self.take_while(char::is_whitespace);
+        if name.is_empty() || !self.rest().starts_with('"') {
+            return Err(PgnError::InvalidTag);
+        }
+        self.pos += 1;
+
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        loop {
+            match chars.next() {
+                Some((i, '"')) => {
+                    self.pos += i + 1;
+                    break;
+                }
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => return Err(PgnError::InvalidTag),
+                },
+                Some((_, '\n')) | None => return Err(PgnError::InvalidTag),
+                Some((_, c)) => value.push(c),
+            }
+        }
+        self.take_while(char::is_whitespace);
+        if !self.rest().starts_with(']') {
+            return Err(PgnError::InvalidTag);
+        }
+        self.pos += 1;
+        Ok(Token::Tag(name.to_string(), value))
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn append_comment(comment: &mut Option<String>, text: String) {
+    *comment = match comment.take() {
+        Some(old) => Some(old + " " + &text),
+        None => Some(text),
+    };
+}
+
+impl<'a> Parser<'a> {
+    fn new(pgn: &'a str) -> Self {
+        Parser {
+            lexer: Lexer::new(pgn),
+            peeked: None,
+            result: None,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, PgnError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lexer.next_token()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<Token>, PgnError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.lexer.next_token(),
+        }
+    }
+
+    // Reads moves from `board` until the end of the line, which is a closing
+    // parenthesis for a variation and the termination marker for the main line.
+    // The board is left unchanged.
+    fn parse_line(&mut self, board: &mut Board, nested: bool) -> Result<Vec<PgnMove>, PgnError> {
+        let mut line: Vec<PgnMove> = Vec::new();
+        let mut states = Vec::new();
+        let mut comment_before = None;
+        loop {
+            let token = match self.next()? {
+                Some(token) => token,
+                None if nested => return Err(PgnError::UnbalancedVariation),
+                None => break,
+            };
+            match token {
+                Token::San(san) => {
+                    let mv = board
+                        .parse_san(&san)
+                        .map_err(|reason| PgnError::InvalidMove {
+                            move_number: board.fullmove_count,
+                            san,
+                            reason,
+                        })?;
+                    states.push(board.make_move(mv));
+                    line.push(PgnMove {
+                        mv,
+                        annotation: Annotation {
+                            comment_before: comment_before.take(),
+                            ..Annotation::default()
+                        },
+                    });
+                }
+                Token::Comment(text) => match line.last_mut() {
+                    Some(last) => append_comment(&mut last.annotation.comment, text),
+                    None => append_comment(&mut comment_before, text),
+                },
+                Token::Nag(nag) => match line.last_mut() {
+                    Some(last) => last.annotation.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${}", nag))),
+                },
+                Token::VariationStart => {
+                    let state = *states.last().ok_or(PgnError::UnbalancedVariation)?;
+                    board.undo_move(state);
+                    let variation = self.parse_line(board, true)?;
+                    board.make_move(state.last_move);
+                    if !variation.is_empty() {
+                        let last = line.last_mut().unwrap();
+                        last.annotation.variations.push(variation);
+                    }
+                }
+                Token::VariationEnd if nested => break,
+                Token::VariationEnd => return Err(PgnError::UnbalancedVariation),
+                Token::Result(result) if !nested => {
+                    self.result = Some(result);
+                    break;
+                }
+                Token::Result(result) => return Err(PgnError::UnexpectedToken(result)),
+                Token::Tag(name, _) => return Err(PgnError::UnexpectedToken(name)),
+            }
+        }
+        for state in states.into_iter().rev() {
+            board.undo_move(state);
+        }
+        Ok(line)
+    }
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> Self {
+        PgnReader {
+            reader,
+            pending: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<Game, PgnError>;
+
+    // A game ends where a tag line follows its movetext, so only one game is
+    // held in memory at a time
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = std::mem::take(&mut self.pending);
+        let mut in_movetext = false;
+        let mut in_comment = false;
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(err) => return Some(Err(PgnError::Io(err.kind()))),
+            }
+            let trimmed = line.trim_start();
+            if !in_comment && trimmed.starts_with('[') {
+                if in_movetext {
+                    self.pending = line;
+                    break;
+                }
+            } else if !trimmed.is_empty() && !trimmed.starts_with('%') {
+                in_movetext = true;
+                // Brackets inside a multi-line comment don't start a new game
+                for c in trimmed.chars() {
+                    match c {
+                        '{' if !in_comment => in_comment = true,
+                        '}' if in_comment => in_comment = false,
+                        ';' if !in_comment => break,
+                        _ => {}
+                    }
+                }
+            }
+            text.push_str(&line);
+        }
+        if text.trim().is_empty() {
+            None
+        } else {
+            Some(Game::from_pgn(&text))
+        }
+    }
+}
+
+impl Error for PgnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PgnError::InvalidFen(err) => Some(err),
+            PgnError::InvalidMove { reason, .. } => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::Io(kind) => write!(f, "Error reading PGN: {:?}", kind),
+            PgnError::InvalidTag => write!(f, "Invalid PGN tag pair"),
+            PgnError::InvalidFen(err) => write!(f, "Invalid PGN FEN tag: {}", err),
+            PgnError::InvalidMove {
+                move_number,
+                san,
+                reason,
+            } => write!(
+                f,
+                "Invalid PGN move {} at move {}: {}",
+                san, move_number, reason
+            ),
+            PgnError::UnexpectedToken(token) => write!(f, "Unexpected PGN token {}", token),
+            PgnError::UnterminatedComment => write!(f, "Unterminated PGN comment"),
+            PgnError::UnbalancedVariation => write!(f, "Unbalanced PGN variation"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MoveType, OutcomeReason, Square};
+    use Square::*;
+
+    const IMMORTAL_GAME: &str = r#"[Event "London"]
+[Site "London ENG"]
+[Date "1851.06.21"]
+[Round "?"]
+[White "Adolf Anderssen"]
+[Black "Lionel Kieseritzky"]
+[Result "1-0"]
+[ECO "C33"]
+
+1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1 b5 5. Bxb5 Nf6 6. Nf3 Qh6 7. d3 Nh5 8. Nh4
+Qg5 9. Nf5 c6 10. g4 Nf6 11. Rg1 cxb5 12. h4 Qg6 13. h5 Qg5 14. Qf3 Ng8 15. Bxf4
+Qf6 16. Nc3 Bc5 17. Nd5 Qxb2 18. Bd6 Bxg1 19. e5 Qxa1+ 20. Ke2 Na6 21. Nxg7+ Kd8
+22. Qf6+ Nxf6 23. Be7# 1-0
+"#;
+
+    #[test]
+    fn test_pgn_import() {
+        let game = Game::from_pgn(IMMORTAL_GAME).unwrap();
+        assert_eq!(game.moves().count(), 45);
+        assert_eq!(game.tag("White"), Some("Adolf Anderssen"));
+        assert_eq!(game.tag("ECO"), Some("C33"));
+        assert_eq!(game.tag("Result"), Some("1-0"));
+        assert_eq!(game.outcome().unwrap().reason, OutcomeReason::Checkmate);
+        assert_eq!(game.start_board().to_fen(), Board::default().to_fen());
+    }
+
+    #[test]
+    fn test_pgn_round_trip() {
+        let game = Game::from_pgn(IMMORTAL_GAME).unwrap();
+        let pgn = game.to_pgn();
+        assert!(pgn.starts_with("[Event \"London\"]\n[Site \"London ENG\"]\n"));
+        assert!(pgn.contains("[ECO \"C33\"]\n\n1. e4 e5 2. f4 exf4 3. Bc4 Qh4+ 4. Kf1"));
+        assert!(pgn.ends_with("23. Be7# 1-0\n"));
+        assert!(pgn.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        let again = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(again.to_pgn(), pgn);
+        assert_eq!(again.board.to_fen(), game.board.to_fen());
+    }
+
+    #[test]
+    fn test_pgn_export_defaults() {
+        let mut game = Game::default();
+        game.make_move(Move::normal(E2, E4));
+        let pgn = game.to_pgn();
+        assert_eq!(
+            pgn,
+            "[Event \"?\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n[Round \"?\"]\n\
+             [White \"?\"]\n[Black \"?\"]\n[Result \"*\"]\n\n1. e4 *\n"
+        );
+        game.set_tag("White", "Say \"hi\"");
+        assert!(game.to_pgn().contains("[White \"Say \\\"hi\\\"\"]"));
+        assert_eq!(
+            Game::from_pgn(&game.to_pgn()).unwrap().tag("White"),
+            Some("Say \"hi\"")
+        );
+    }
+
+    #[test]
+    fn test_pgn_setup() {
+        let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 0 40";
+        let pgn = format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n40... Kd7 41. e4 Ke6 *", fen);
+        let game = Game::from_pgn(&pgn).unwrap();
+        assert_eq!(game.moves().count(), 3);
+        assert_eq!(game.start_board().to_fen(), fen);
+
+        let pgn = game.to_pgn();
+        assert!(pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)));
+        assert!(pgn.contains("40... Kd7 41. e4 Ke6 *"));
+        assert!(matches!(
+            Game::from_pgn("[FEN \"8/8 w - - 0 1\"]\n*"),
+            Err(PgnError::InvalidFen(_))
+        ));
+    }
+
+    #[test]
+    fn test_pgn_annotations() {
+        let pgn = "{Opening} 1. e4 $1 {Best by test} e5 2. Nf3!? (2. f4 exf4 \
+                   (2... d5) 3. Nf3) (2. Bc4) 2... Nc6 ; Knight out\n3. Bb5 *";
+        let game = Game::from_pgn(pgn).unwrap();
+        assert_eq!(game.moves().count(), 5);
+        let annotations = game.annotations();
+        assert_eq!(annotations[0].comment_before.as_deref(), Some("Opening"));
+        assert_eq!(annotations[0].nags, vec![1]);
+        assert_eq!(annotations[0].comment.as_deref(), Some("Best by test"));
+        assert_eq!(annotations[2].nags, vec![5]);
+        assert_eq!(annotations[3].comment.as_deref(), Some("Knight out"));
+
+        let variations = &annotations[2].variations;
+        assert_eq!(variations.len(), 2);
+        assert_eq!(variations[0].len(), 3);
+        assert_eq!(variations[0][0].mv, Move::new(F2, F4, MoveType::DoublePush));
+        assert_eq!(
+            variations[0][1].annotation.variations[0][0].mv,
+            Move::new(D7, D5, MoveType::DoublePush)
+        );
+        assert_eq!(variations[1][0].mv, Move::normal(F1, C4));
+
+        let exported = game.to_pgn();
+        assert!(collapse_whitespace(&exported).contains(
+            "{Opening} 1. e4 $1 {Best by test} 1... e5 2. Nf3 $5 (2. f4 exf4 (2... d5) \
+             3. Nf3) (2. Bc4) 2... Nc6 {Knight out} 3. Bb5 *"
+        ));
+        assert_eq!(
+            Game::from_pgn(&exported).unwrap().annotations(),
+            annotations
+        );
+    }
+
+    #[test]
+    fn test_pgn_errors() {
+        assert!(matches!(
+            Game::from_pgn("1. e4 e5 2. Ke3 *"),
+            Err(PgnError::InvalidMove {
+                move_number: 2,
+                reason: SanError::IllegalMove,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Game::from_pgn("1. e4 (1. d4 *"),
+            Err(PgnError::UnexpectedToken(_))
+        ));
+        assert!(matches!(
+            Game::from_pgn("1. e4 (1. d4"),
+            Err(PgnError::UnbalancedVariation)
+        ));
+        assert!(matches!(
+            Game::from_pgn("1. e4 e5) *"),
+            Err(PgnError::UnbalancedVariation)
+        ));
+        assert!(matches!(
+            Game::from_pgn("1. e4 {unclosed *"),
+            Err(PgnError::UnterminatedComment)
+        ));
+        assert!(matches!(
+            Game::from_pgn("[Event London]\n*"),
+            Err(PgnError::InvalidTag)
+        ));
+    }
+
+    #[test]
+    fn test_pgn_reader() {
+        let archive = format!(
+            "{}\n[Event \"Second\"]\n\n1. d4 {{[not a tag\n[still a comment]}} d5 1/2-1/2\n\n\
+             % skipped\n[Event \"Third\"]\n[Result \"0-1\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n",
+            IMMORTAL_GAME
+        );
+        let games: Vec<Game> = PgnReader::new(archive.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[0].moves().count(), 45);
+        assert_eq!(games[1].tag("Event"), Some("Second"));
+        assert_eq!(games[1].tag("Result"), Some("1/2-1/2"));
+        assert_eq!(
+            games[1].annotations()[0].comment.as_deref(),
+            Some("[not a tag [still a comment]")
+        );
+        assert_eq!(games[2].outcome().unwrap().winner, Some(Color::Black));
+        assert_eq!(PgnReader::new("\n\n".as_bytes()).count(), 0);
+    }
+}
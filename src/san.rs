@@ -0,0 +1,371 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Board, BoardPiece, File, Move, MoveType, PieceType, Rank, Square};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SanError {
+    // Not shaped like a SAN move at all
+    InvalidSyntax,
+    // Well formed, but no legal move matches
+    IllegalMove,
+    // More than one legal move matches
+    AmbiguousMove,
+}
+
+// Parts of a SAN move, before matching against the board
+struct SanMove {
+    piece_type: PieceType,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    to: Square,
+    promotion: Option<PieceType>,
+}
+
+impl Board {
+    // Standard Algebraic Notation for a legal move, like `Nbd7`, `exd6`, `e8=Q+`
+    // or `O-O-O#`
+    pub fn move_to_san(&self, mv: Move) -> String {
+        let mut san = String::new();
+        let piece_type = match self.piece_at(mv.from()) {
+            BoardPiece::Piece(piece) => piece.piece_type(),
+            BoardPiece::Empty => return mv.to_string(),
+        };
+
+        if mv.move_type() == MoveType::Castle {
+            san.push_str(if mv.to().file() == File::G {
+                "O-O"
+            } else {
+                "O-O-O"
+            });
+        } else {
+            let is_capture = self.piece_at(mv.to()) != BoardPiece::Empty
+                || mv.move_type() == MoveType::EnPassant;
+            if piece_type == PieceType::Pawn {
+                if is_capture {
+                    san.push(file_char(mv.from().file()));
+                }
+            } else {
+                san.push(piece_char(piece_type));
+                san.push_str(&self.disambiguation(mv));
+            }
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&mv.to().to_string());
+            if let MoveType::Promotion(promo) = mv.move_type() {
+                san.push('=');
+                san.push(piece_char(promo));
+            }
+        }
+
+        let mut board = self.clone();
+        board.make_move(mv);
+        if board.is_in_check() {
+            san.push(if board.gen_legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+
+    // Shortest origin hint telling `mv` apart from other moves of the same piece
+    // type to the same square
+    fn disambiguation(&self, mv: Move) -> String {
+        let others: Vec<Square> = self
+            .gen_legal_moves()
+            .into_iter()
+            .filter(|other| {
+                other.to() == mv.to()
+                    && other.from() != mv.from()
+                    && self.piece_at(other.from()) == self.piece_at(mv.from())
+                    && other.move_type() != MoveType::Castle
+            })
+            .map(|other| other.from())
+            .collect();
+        if others.is_empty() {
+            String::new()
+        } else if others.iter().all(|sq| sq.file() != mv.from().file()) {
+            file_char(mv.from().file()).to_string()
+        } else if others.iter().all(|sq| sq.rank() != mv.from().rank()) {
+            rank_char(mv.from().rank()).to_string()
+        } else {
+            mv.from().to_string()
+        }
+    }
+
+    // Resolve a SAN move against the legal moves of this position. Accepts
+    // common variations like `0-0`, `e8Q`, `exd6 e.p.` and trailing `+`, `#`,
+    // `!` or `?` annotations.
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        let san = san
+            .trim()
+            .trim_end_matches("e.p.")
+            .trim_end()
+            .trim_end_matches(&['+', '#', '!', '?'][..]);
+
+        let legal = self.gen_legal_moves();
+        let castle_file = match san {
+            "O-O" | "0-0" => Some(File::G),
+            "O-O-O" | "0-0-0" => Some(File::C),
+            _ => None,
+        };
+        let candidates: Vec<Move> = if let Some(file) = castle_file {
+            legal
+                .into_iter()
+                .filter(|mv| mv.move_type() == MoveType::Castle && mv.to().file() == file)
+                .collect()
+        } else {
+            let parsed = SanMove::from_str(san)?;
+            legal
+                .into_iter()
+                .filter(|&mv| parsed.matches(self, mv))
+                .collect()
+        };
+
+        match candidates.len() {
+            0 => Err(SanError::IllegalMove),
+            1 => Ok(candidates[0]),
+            _ => Err(SanError::AmbiguousMove),
+        }
+    }
+}
+
+impl SanMove {
+    fn matches(&self, board: &Board, mv: Move) -> bool {
+        let piece_type = match board.piece_at(mv.from()) {
+            BoardPiece::Piece(piece) => piece.piece_type(),
+            BoardPiece::Empty => return false,
+        };
+        let promotion = match mv.move_type() {
+            MoveType::Promotion(promo) => Some(promo),
+            MoveType::Castle => return false,
+            _ => None,
+        };
+        piece_type == self.piece_type
+            && mv.to() == self.to
+            && self.from_file.map_or(true, |file| mv.from().file() == file)
+            && self.from_rank.map_or(true, |rank| mv.from().rank() == rank)
+            && promotion == self.promotion
+    }
+}
+
+impl FromStr for SanMove {
+    type Err = SanError;
+
+    fn from_str(san: &str) -> Result<Self, Self::Err> {
+        if !san.is_ascii() {
+            return Err(SanError::InvalidSyntax);
+        }
+        let mut chars: Vec<char> = san.chars().filter(|&c| c != 'x' && c != ':').collect();
+
+        let piece_type = match chars.first() {
+            Some(&c) if c.is_ascii_uppercase() => {
+                chars.remove(0);
+                piece_from_char(c).ok_or(SanError::InvalidSyntax)?
+            }
+            _ => PieceType::Pawn,
+        };
+
+        // Promotion as `=Q`, `(Q)` or just `Q`
+        let mut promotion = None;
+        if chars.last() == Some(&')') {
+            chars.pop();
+        }
+        if let Some(&c) = chars.last() {
+            if c.is_ascii_alphabetic() && chars.len() > 2 {
+                chars.pop();
+                if matches!(chars.last(), Some('=') | Some('(')) {
+                    chars.pop();
+                }
+                let promo =
+                    piece_from_char(c.to_ascii_uppercase()).ok_or(SanError::InvalidSyntax)?;
+                if matches!(promo, PieceType::Pawn | PieceType::King) {
+                    return Err(SanError::InvalidSyntax);
+                }
+                promotion = Some(promo);
+            }
+        }
+
+        if chars.len() < 2 || chars.len() > 4 {
+            return Err(SanError::InvalidSyntax);
+        }
+        let to_str: String = chars[chars.len() - 2..].iter().collect();
+        let to = Square::from_str(&to_str).map_err(|_| SanError::InvalidSyntax)?;
+
+        let (mut from_file, mut from_rank) = (None, None);
+        for &c in chars[..chars.len() - 2].iter() {
+            if let Ok(file) = File::try_from(c) {
+                from_file = Some(file);
+            } else if let Ok(rank) = Rank::try_from(c) {
+                from_rank = Some(rank);
+            } else {
+                return Err(SanError::InvalidSyntax);
+            }
+        }
+
+        Ok(Self {
+            piece_type,
+            from_file,
+            from_rank,
+            to,
+            promotion,
+        })
+    }
+}
+
+fn piece_char(piece_type: PieceType) -> char {
+    piece_type.to_string().to_ascii_uppercase().pop().unwrap()
+}
+
+fn piece_from_char(letter: char) -> Option<PieceType> {
+    match letter {
+        'K' | 'Q' | 'R' | 'B' | 'N' => PieceType::try_from(letter.to_ascii_lowercase()).ok(),
+        _ => None,
+    }
+}
+
+fn file_char(file: File) -> char {
+    (b'a' + file as u8) as char
+}
+
+fn rank_char(rank: Rank) -> char {
+    (b'1' + rank as u8) as char
+}
+
+impl Error for SanError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            SanError::InvalidSyntax => "Invalid SAN move syntax",
+            SanError::IllegalMove => "No legal move matches SAN",
+            SanError::AmbiguousMove => "SAN move is ambiguous",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Square::*;
+
+    fn san_round_trip(fen: &str, san: &str, mv: Move) {
+        let board = Board::from_fen(fen).unwrap();
+        assert_eq!(board.move_to_san(mv), san);
+        assert_eq!(board.parse_san(san), Ok(mv));
+    }
+
+    #[test]
+    fn test_san_basic() {
+        let fen = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        san_round_trip(fen, "e4", Move::new(E2, E4, MoveType::DoublePush));
+        san_round_trip(fen, "e3", Move::normal(E2, E3));
+        san_round_trip(fen, "Nf3", Move::normal(G1, F3));
+        let fen = "rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2";
+        san_round_trip(fen, "exd5", Move::normal(E4, D5));
+        san_round_trip(fen, "Bb5+", Move::normal(F1, B5));
+    }
+
+    #[test]
+    fn test_san_special() {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        san_round_trip(fen, "O-O", Move::new(E1, G1, MoveType::Castle));
+        san_round_trip(fen, "O-O-O", Move::new(E1, C1, MoveType::Castle));
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        san_round_trip(fen, "exd6", Move::new(E5, D6, MoveType::EnPassant));
+        let fen = "4k3/1P6/8/8/8/8/8/4K3 w - - 0 1";
+        san_round_trip(
+            fen,
+            "b8=Q+",
+            Move::new(B7, B8, MoveType::Promotion(PieceType::Queen)),
+        );
+        san_round_trip(
+            fen,
+            "b8=N",
+            Move::new(B7, B8, MoveType::Promotion(PieceType::Knight)),
+        );
+        // Under-promotions are legal even when the search skips them
+        san_round_trip(
+            fen,
+            "b8=R+",
+            Move::new(B7, B8, MoveType::Promotion(PieceType::Rook)),
+        );
+        san_round_trip(
+            fen,
+            "b8=B",
+            Move::new(B7, B8, MoveType::Promotion(PieceType::Bishop)),
+        );
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        san_round_trip(fen, "Ra8#", Move::normal(A1, A8));
+    }
+
+    #[test]
+    fn test_san_disambiguation() {
+        // Knights on B1 and F1 can both reach D2
+        let fen = "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1";
+        san_round_trip(fen, "Nbd2", Move::normal(B1, D2));
+        san_round_trip(fen, "Nfd2", Move::normal(F1, D2));
+        // Rooks on A1 and A5 share a file
+        let fen = "4k3/8/8/R7/8/8/8/R3K3 w - - 0 1";
+        san_round_trip(fen, "R1a3", Move::normal(A1, A3));
+        san_round_trip(fen, "R5a3", Move::normal(A5, A3));
+        // Queens on D1, D3 and F1 all reach E2, so D1 needs file and rank
+        let fen = "k7/8/8/8/8/3Q4/8/3Q1QK1 w - - 0 1";
+        san_round_trip(fen, "Qd1e2", Move::normal(D1, E2));
+        san_round_trip(fen, "Q3e2", Move::normal(D3, E2));
+        san_round_trip(fen, "Qfe2", Move::normal(F1, E2));
+    }
+
+    #[test]
+    fn test_parse_san_variants() {
+        let board = Board::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            board.parse_san("0-0"),
+            Ok(Move::new(E1, G1, MoveType::Castle))
+        );
+        assert_eq!(
+            board.parse_san("0-0-0!?"),
+            Ok(Move::new(E1, C1, MoveType::Castle))
+        );
+        assert_eq!(board.parse_san("Rxa8+"), Ok(Move::normal(A1, A8)));
+        let board = Board::from_fen("k7/4P3/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promo = Move::new(E7, E8, MoveType::Promotion(PieceType::Queen));
+        assert_eq!(board.parse_san("e8Q"), Ok(promo));
+        assert_eq!(board.parse_san("e8=Q+"), Ok(promo));
+        assert_eq!(board.parse_san("e8(Q)"), Ok(promo));
+        assert_eq!(board.parse_san("e8q"), Ok(promo));
+        assert_eq!(
+            board.parse_san("e8=R"),
+            Ok(Move::new(E7, E8, MoveType::Promotion(PieceType::Rook)))
+        );
+        assert_eq!(board.parse_san("e8"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("e8=K"), Err(SanError::InvalidSyntax));
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        let ep = Move::new(E5, D6, MoveType::EnPassant);
+        assert_eq!(board.parse_san("exd6 e.p."), Ok(ep));
+        assert_eq!(board.parse_san("ed6"), Ok(ep));
+    }
+
+    #[test]
+    fn test_parse_san_errors() {
+        let board = Board::default();
+        assert_eq!(board.parse_san(""), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("Xe4"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("e9"), Err(SanError::InvalidSyntax));
+        assert_eq!(board.parse_san("e5"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("Nd2"), Err(SanError::IllegalMove));
+        assert_eq!(board.parse_san("O-O"), Err(SanError::IllegalMove));
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1").unwrap();
+        assert_eq!(board.parse_san("Nd2"), Err(SanError::AmbiguousMove));
+    }
+}
@@ -0,0 +1,588 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::eval::PIECE_VALUES;
+use crate::movepick::{Heuristics, MovePicker};
+use crate::{
+    evaluate, Board, BoardPiece, Bound, Color, Game, Move, MoveType, Score, TranspositionTable,
+};
+
+// Deepest the search will go, in plies from the root
+pub const MAX_PLY: usize = 64;
+
+// Kept back from the clock for communication delays
+const MOVE_OVERHEAD: Duration = Duration::from_millis(50);
+// Moves assumed left in the game when the clock doesn't say
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+// Nodes searched between checks of the clock and stop flag
+const CHECK_INTERVAL: u64 = 1024;
+
+// Positional swing allowed for on top of the captured piece when delta pruning
+const DELTA_MARGIN: i32 = 200;
+
+// How long and how deep to search, as given by a protocol front-end
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    // Remaining clock time and increment of each side, indexed by `Color`
+    pub time: [Option<Duration>; 2],
+    pub increment: [Duration; 2],
+    pub moves_to_go: Option<u32>,
+    // Search until stopped, ignoring every other limit
+    pub infinite: bool,
+}
+
+// Progress after each completed iteration, and the final result
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SearchInfo {
+    pub depth: u8,
+    pub score: Score,
+    pub nodes: u64,
+    pub time: Duration,
+    // Permille of the transposition table in use
+    pub hashfull: u16,
+    // Principal variation, starting with the best move
+    pub pv: Vec<Move>,
+}
+
+// Iterative deepening negamax alpha-beta search over a copy of a position
+pub struct Search {
+    board: Board,
+    // Hashes of the positions before the current one, for spotting repetitions
+    hashes: Vec<u64>,
+    tt: Arc<TranspositionTable>,
+    heuristics: Heuristics,
+    // Triangular table, with the best line found from each ply
+    pv: Vec<Vec<Move>>,
+    // Principal variation of the last iteration, searched first in the next
+    prev_pv: Vec<Move>,
+    nodes: u64,
+    start: Instant,
+    deadline: Option<Instant>,
+    node_limit: Option<u64>,
+    stopped: bool,
+    // Whether to search promotions to rook and bishop, which engines may
+    // prune as they are almost never better than a queen
+    pub under_promotions: bool,
+}
+
+impl SearchLimits {
+    // Time to spend on the next move for `color`, or `None` if unlimited
+    pub fn time_budget(&self, color: Color) -> Option<Duration> {
+        if self.infinite {
+            return None;
+        }
+        if let Some(movetime) = self.movetime {
+            return Some(movetime);
+        }
+        let time = self.time[color as usize]?;
+        let moves_to_go = self.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let budget = time / moves_to_go + self.increment[color as usize] * 3 / 4;
+        Some(budget.min(time.saturating_sub(MOVE_OVERHEAD)))
+    }
+}
+
+impl SearchInfo {
+    pub fn best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    // Nodes per second
+    pub fn nps(&self) -> u64 {
+        let micros = self.time.as_micros().max(1);
+        (u128::from(self.nodes) * 1_000_000 / micros) as u64
+    }
+}
+
+impl Search {
+    // Searches the current position of `game`, using its history to detect
+    // repetitions. Results are shared with other searches through `tt`.
+    pub fn new(game: &Game, tt: Arc<TranspositionTable>) -> Self {
+        Self::with_history(game.board.clone(), game.position_hashes(), tt)
+    }
+
+    // Searches `board`, with `hashes` of the positions leading up to it
+    pub fn with_history(board: Board, hashes: Vec<u64>, tt: Arc<TranspositionTable>) -> Self {
+        Search {
+            board,
+            hashes,
+            tt,
+            heuristics: Heuristics::new(),
+            pv: vec![Vec::new(); MAX_PLY + 1],
+            prev_pv: Vec::new(),
+            nodes: 0,
+            start: Instant::now(),
+            deadline: None,
+            node_limit: None,
+            stopped: false,
+            under_promotions: true,
+        }
+    }
+
+    // Deepens one ply at a time until a limit is hit or `stop` is set, calling
+    // `on_info` after each completed iteration. Returns the last completed
+    // iteration, falling back to the first legal move if there wasn't one.
+    pub fn run<F>(&mut self, limits: SearchLimits, stop: &AtomicBool, mut on_info: F) -> SearchInfo
+    where
+        F: FnMut(&SearchInfo),
+    {
+        self.start = Instant::now();
+        let budget = limits.time_budget(self.board.turn);
+        self.deadline = budget.map(|budget| self.start + budget);
+        self.node_limit = if limits.infinite { None } else { limits.nodes };
+        self.nodes = 0;
+        self.stopped = false;
+        self.tt.new_search();
+        let max_depth = match limits.depth {
+            Some(depth) if !limits.infinite => depth.clamp(1, MAX_PLY as u8),
+            _ => MAX_PLY as u8,
+        };
+
+        let mut best = SearchInfo {
+            pv: self.board.gen_legal_moves().into_iter().take(1).collect(),
+            ..SearchInfo::default()
+        };
+        for depth in 1..=max_depth {
+            let score = self.negamax(depth, 0, -Score::INFINITE, Score::INFINITE, None, stop);
+            if self.stopped {
+                break;
+            }
+            self.prev_pv = self.pv[0].clone();
+            best = SearchInfo {
+                depth,
+                score,
+                nodes: self.nodes,
+                time: self.start.elapsed(),
+                hashfull: self.tt.hashfull(),
+                pv: self.prev_pv.clone(),
+            };
+            on_info(&best);
+
+            // A mate within the horizon won't get any shorter
+            let mate_found = score.is_mate() && Score::MATE.0 - score.0.abs() <= i32::from(depth);
+            // The next iteration likely takes longer than all the ones before
+            let out_of_time = budget.is_some_and(|budget| best.time * 2 > budget);
+            if best.pv.is_empty() || (!limits.infinite && (mate_found || out_of_time)) {
+                break;
+            }
+        }
+        best
+    }
+
+    // `prev` is the move which led here, for counter-move ordering
+    fn negamax(
+        &mut self,
+        depth: u8,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+        prev: Option<Move>,
+        stop: &AtomicBool,
+    ) -> Score {
+        self.pv[ply].clear();
+        if self.should_stop(stop) {
+            return Score::ZERO;
+        }
+        self.nodes += 1;
+        if ply > 0 && self.is_draw() {
+            return Score::DRAW;
+        }
+        if depth == 0 || ply >= MAX_PLY {
+            return self.quiescence(ply, alpha, beta, stop);
+        }
+
+        let hash = self.board.hash();
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry.filter(|entry| ply > 0 && entry.depth >= depth) {
+            // Exact scores inside the window are searched again anyway, so the
+            // principal variation isn't cut short
+            let score = score_from_tt(entry.score, ply);
+            let cutoff = match entry.bound {
+                Bound::Exact => score <= alpha || score >= beta,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff {
+                return score.clamp(alpha, beta);
+            }
+        }
+        let tt_move = tt_entry.and_then(|entry| entry.mv);
+
+        // Last iteration's move stands in if the table lost this position
+        let first = tt_move.or_else(|| self.prev_pv.get(ply).copied());
+        let counter = prev.and_then(|prev| self.heuristics.counter(&self.board, prev));
+        let mut picker = MovePicker::new(first, self.heuristics.killers(ply), counter)
+            .under_promotions(self.under_promotions);
+        let original_alpha = alpha;
+        let mut best_move = tt_move;
+        let mut move_count = 0;
+        let mut quiets_tried = Vec::new();
+        while let Some(mv) = picker.next(&self.board, &self.heuristics) {
+            move_count += 1;
+            let quiet = self.board.piece_at(mv.to()) == BoardPiece::Empty
+                && matches!(
+                    mv.move_type(),
+                    MoveType::Normal | MoveType::DoublePush | MoveType::Castle
+                );
+            self.hashes.push(self.board.hash());
+            let state = self.board.make_move(mv);
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, Some(mv), stop);
+            self.board.undo_move(state);
+            self.hashes.pop();
+            if self.stopped {
+                return Score::ZERO;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(mv);
+                let (head, tail) = self.pv.split_at_mut(ply + 1);
+                head[ply].clear();
+                head[ply].push(mv);
+                head[ply].extend_from_slice(&tail[0]);
+                if alpha >= beta {
+                    if quiet {
+                        self.heuristics.record_cutoff(
+                            &self.board,
+                            mv,
+                            ply,
+                            prev,
+                            depth,
+                            &quiets_tried,
+                        );
+                    }
+                    break;
+                }
+            }
+            if quiet {
+                quiets_tried.push(mv);
+            }
+        }
+        if move_count == 0 {
+            return if self.board.is_in_check() {
+                Score::mated_in(ply as u32)
+            } else {
+                Score::DRAW
+            };
+        }
+
+        let bound = if alpha >= beta {
+            Bound::Lower
+        } else if alpha > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.tt
+            .store(hash, best_move, score_to_tt(alpha, ply), depth, bound);
+        alpha
+    }
+
+    // Searches captures until the position is quiet, so the static evaluation
+    // isn't taken in the middle of an exchange. The side to move may stand pat
+    // on the evaluation instead, unless in check, where every evasion is searched.
+    fn quiescence(
+        &mut self,
+        ply: usize,
+        mut alpha: Score,
+        beta: Score,
+        stop: &AtomicBool,
+    ) -> Score {
+        self.pv[ply].clear();
+        if self.should_stop(stop) {
+            return Score::ZERO;
+        }
+        self.nodes += 1;
+        if self.board.is_insufficient_material() {
+            return Score::DRAW;
+        }
+
+        let in_check = self.board.is_in_check();
+        let stand_pat = evaluate(&self.board);
+        if ply >= MAX_PLY {
+            return if in_check { Score::DRAW } else { stand_pat };
+        }
+        let mut picker = if in_check {
+            MovePicker::new(None, [None; 2], None)
+        } else {
+            if stand_pat >= beta {
+                return beta;
+            }
+            alpha = alpha.max(stand_pat);
+            MovePicker::captures()
+        }
+        .under_promotions(self.under_promotions);
+
+        let mut move_count = 0;
+        while let Some(mv) = picker.next(&self.board, &self.heuristics) {
+            move_count += 1;
+            // Delta pruning: skip captures which couldn't raise alpha even with
+            // the captured piece thrown in
+            if !in_check && !matches!(mv.move_type(), MoveType::Promotion(_)) {
+                let gain = match self.board.piece_at(mv.to()) {
+                    BoardPiece::Piece(piece) => PIECE_VALUES[piece.piece_type() as usize],
+                    BoardPiece::Empty => PIECE_VALUES[0],
+                };
+                if stand_pat + Score(gain + DELTA_MARGIN) <= alpha {
+                    continue;
+                }
+            }
+            // Captures losing material in the exchange are left out
+            if !in_check && !self.board.see_ge(mv, 0) {
+                continue;
+            }
+            let state = self.board.make_move(mv);
+            let score = -self.quiescence(ply + 1, -beta, -alpha, stop);
+            self.board.undo_move(state);
+            if self.stopped {
+                return Score::ZERO;
+            }
+            if score > alpha {
+                alpha = score;
+                if alpha >= beta {
+                    break;
+                }
+            }
+        }
+        if in_check && move_count == 0 {
+            return Score::mated_in(ply as u32);
+        }
+        alpha
+    }
+
+    // Repetition of any earlier position since the last irreversible move, or
+    // a draw by the fifty-move rule or material
+    fn is_draw(&self) -> bool {
+        if self.board.halfmove_clock >= 100 || self.board.is_insufficient_material() {
+            return true;
+        }
+        let hash = self.board.hash();
+        let reversible = (self.board.halfmove_clock as usize).min(self.hashes.len());
+        self.hashes[self.hashes.len() - reversible..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|&earlier| earlier == hash)
+    }
+
+    fn should_stop(&mut self, stop: &AtomicBool) -> bool {
+        if self.stopped {
+            return true;
+        }
+        if self.node_limit.is_some_and(|limit| self.nodes >= limit) {
+            self.stopped = true;
+        } else if self.nodes % CHECK_INTERVAL == 0 {
+            let timed_out = self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+            self.stopped = timed_out || stop.load(Ordering::Relaxed);
+        }
+        self.stopped
+    }
+}
+
+// Mate scores are stored as the distance from the node rather than the root,
+// so they stay right when the node is reached at another ply
+fn score_to_tt(score: Score, ply: usize) -> Score {
+    match score {
+        score if !score.is_mate() => score,
+        Score(s) if s > 0 => Score(s + ply as i32),
+        Score(s) => Score(s - ply as i32),
+    }
+}
+
+fn score_from_tt(score: Score, ply: usize) -> Score {
+    match score {
+        score if !score.is_mate() => score,
+        Score(s) if s > 0 => Score(s - ply as i32),
+        Score(s) => Score(s + ply as i32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square;
+    use Square::*;
+
+    fn search(fen: &str, limits: SearchLimits) -> SearchInfo {
+        let game = Game::from_fen(fen).unwrap();
+        Search::new(&game, table()).run(limits, &AtomicBool::new(false), |_| {})
+    }
+
+    fn table() -> Arc<TranspositionTable> {
+        Arc::new(TranspositionTable::new(1))
+    }
+
+    fn depth(depth: u8) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let mut limits = SearchLimits::default();
+        assert_eq!(limits.time_budget(Color::White), None);
+        limits.time = [Some(Duration::from_secs(60)), Some(Duration::from_secs(1))];
+        limits.increment = [Duration::from_secs(4), Duration::ZERO];
+        assert_eq!(
+            limits.time_budget(Color::White),
+            Some(Duration::from_secs(5))
+        );
+        limits.moves_to_go = Some(1);
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_secs(1) - MOVE_OVERHEAD)
+        );
+        limits.movetime = Some(Duration::from_millis(300));
+        assert_eq!(
+            limits.time_budget(Color::Black),
+            Some(Duration::from_millis(300))
+        );
+        limits.infinite = true;
+        assert_eq!(limits.time_budget(Color::Black), None);
+    }
+
+    #[test]
+    fn test_search_mate() {
+        // Back rank mate in one
+        let info = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", depth(4));
+        assert_eq!(info.best_move(), Some(Move::normal(A1, A8)));
+        assert_eq!(info.score, Score::mate_in(1));
+        // Quiescence finds the mated side has no evasions, so one ply is enough
+        assert_eq!(info.depth, 1);
+
+        // Every Black move allows mate next
+        let info = search("7k/p4K2/8/8/8/8/8/6R1 b - - 0 1", depth(4));
+        assert_eq!(info.score, Score::mated_in(2));
+        assert_eq!(info.pv.len(), 2);
+    }
+
+    #[test]
+    fn test_search_wins_material() {
+        // Knight fork of king and queen
+        let info = search("q3k3/8/8/1N6/8/8/4P3/4K3 w - - 0 1", depth(3));
+        assert_eq!(info.best_move(), Some(Move::normal(B5, C7)));
+        assert_eq!(info.pv.len(), 3);
+        assert_eq!(info.pv[2], Move::normal(C7, A8));
+        assert!(info.score.0 > 200);
+    }
+
+    #[test]
+    fn test_search_quiescence() {
+        // The pawn on d5 is defended, so taking it loses the queen
+        let info = search("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1", depth(1));
+        assert_ne!(info.best_move(), Some(Move::normal(D1, D5)));
+        assert!(info.score.0 > 500);
+        // But an undefended one is taken
+        let info = search("4k3/8/8/3p4/8/8/8/3QK3 w - - 0 1", depth(1));
+        assert_eq!(info.best_move(), Some(Move::normal(D1, D5)));
+    }
+
+    #[test]
+    fn test_search_no_moves() {
+        let info = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", depth(3));
+        assert_eq!(info.best_move(), None);
+        assert_eq!(info.score, Score::ZERO);
+    }
+
+    #[test]
+    fn test_search_draws() {
+        let mut game = Game::default();
+        let search = Search::new(&game, table());
+        assert!(!search.is_draw());
+        for &(from, to) in [(G1, F3), (G8, F6), (F3, G1), (F6, G8)].iter() {
+            game.make_move(Move::normal(from, to));
+        }
+        assert!(Search::new(&game, table()).is_draw());
+        // Earlier positions from before an irreversible move don't count
+        game.make_move(Move::new(E2, E4, MoveType::DoublePush));
+        assert!(!Search::new(&game, table()).is_draw());
+
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 100 80").unwrap();
+        assert!(Search::new(&game, table()).is_draw());
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1").unwrap();
+        assert!(Search::new(&game, table()).is_draw());
+    }
+
+    #[test]
+    fn test_search_limits() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let info = search(
+            start,
+            SearchLimits {
+                nodes: Some(5000),
+                ..SearchLimits::default()
+            },
+        );
+        assert!(info.nodes <= 5000);
+        assert!(info.best_move().is_some());
+
+        let info = search(
+            start,
+            SearchLimits {
+                movetime: Some(Duration::from_millis(50)),
+                ..SearchLimits::default()
+            },
+        );
+        // Generous, as loaded machines and debug builds run late
+        assert!(info.time < Duration::from_millis(500));
+        assert!(info.depth >= 1);
+
+        // Stopped before the first iteration still gives a move
+        let game = Game::default();
+        let info = Search::new(&game, table()).run(
+            SearchLimits::default(),
+            &AtomicBool::new(true),
+            |_| panic!("no iteration should complete"),
+        );
+        assert_eq!(info.depth, 0);
+        assert!(info.best_move().is_some());
+    }
+
+    #[test]
+    fn test_search_info_callback() {
+        let game = Game::default();
+        let mut depths = Vec::new();
+        let info = Search::new(&game, table()).run(depth(3), &AtomicBool::new(false), |info| {
+            depths.push(info.depth);
+            assert_eq!(info.pv.len(), info.depth as usize);
+        });
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(info.depth, 3);
+    }
+
+    #[test]
+    fn test_search_transpositions() {
+        let game = Game::from_fen("q3k3/8/8/1N6/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let tt = table();
+        let stop = AtomicBool::new(false);
+        let first = Search::new(&game, Arc::clone(&tt)).run(depth(5), &stop, |_| {});
+        assert!(first.hashfull > 0);
+        let entry = tt.probe(game.board.hash()).unwrap();
+        assert_eq!(entry.mv, first.best_move());
+        assert_eq!(entry.depth, 5);
+
+        // A second search reuses what the first stored
+        let second = Search::new(&game, tt).run(depth(5), &stop, |_| {});
+        assert_eq!(second.best_move(), first.best_move());
+        assert_eq!(second.score, first.score);
+        assert!(second.nodes < first.nodes);
+    }
+
+    #[test]
+    fn test_tt_mate_scores() {
+        // Mate in 3 plies from a node 4 plies deep is mate in 7 from the root
+        let stored = score_to_tt(Score::mate_in(7), 4);
+        assert_eq!(stored, Score::mate_in(3));
+        assert_eq!(score_from_tt(stored, 10), Score::mate_in(13));
+        assert_eq!(score_to_tt(Score::mated_in(6), 2), Score::mated_in(4));
+        assert_eq!(score_from_tt(Score::mated_in(4), 2), Score::mated_in(6));
+        assert_eq!(score_to_tt(Score(-250), 9), Score(-250));
+    }
+}
@@ -0,0 +1,186 @@
+use crate::attacks;
+use crate::eval::PIECE_VALUES;
+use crate::{Bitboard, Board, BoardPiece, Color, Move, MoveType, PieceType, Rank, Square};
+
+// Order in which recaptures are tried, cheapest first
+const RECAPTURE_ORDER: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+// Longest possible capture sequence on one square, plus the first move
+const MAX_EXCHANGE: usize = 33;
+
+fn value(piece_type: PieceType) -> i32 {
+    PIECE_VALUES[piece_type as usize]
+}
+
+impl Board {
+    // Static Exchange Evaluation: material won by the side to move after `mv`
+    // and the best sequence of recaptures on its target square, each side
+    // recapturing with its least valuable piece and stopping when that would
+    // lose material. Sliders lined up behind others join in as the square opens
+    // up, and pawns recapturing on the last rank promote to queens. Pins are
+    // ignored. Quiet moves score zero, or less if the piece can be won.
+    pub fn see(&self, mv: Move) -> i32 {
+        let (from, to) = (mv.from(), mv.to());
+        let mut piece_type = match self.piece_at(from) {
+            BoardPiece::Piece(piece) => piece.piece_type(),
+            BoardPiece::Empty => return 0,
+        };
+        let mut occupied = self.occupied();
+        let mut gain = [0; MAX_EXCHANGE];
+        gain[0] = match (self.piece_at(to), mv.move_type()) {
+            (_, MoveType::Castle) => return 0,
+            (BoardPiece::Piece(captured), _) => value(captured.piece_type()),
+            (_, MoveType::EnPassant) => {
+                occupied.remove(to.down(self.turn).unwrap());
+                value(PieceType::Pawn)
+            }
+            _ => 0,
+        };
+        if let MoveType::Promotion(promo) = mv.move_type() {
+            gain[0] += value(promo) - value(PieceType::Pawn);
+            piece_type = promo;
+        }
+        occupied.remove(from);
+
+        let promotes = to.rank() == Rank::R1 || to.rank() == Rank::R8;
+        let mut side = !self.turn;
+        let mut depth = 0;
+        loop {
+            let attackers = self.attackers_through(to, occupied) & occupied;
+            let ours = attackers & self.occupied_by(side);
+            let (sq, attacker) = match RECAPTURE_ORDER.iter().find_map(|&piece_type| {
+                let sq = (ours & self.pieces(piece_type)).iter().next()?;
+                Some((sq, piece_type))
+            }) {
+                Some(found) => found,
+                None => break,
+            };
+            // The king may only take when nothing can take it back
+            if attacker == PieceType::King && !(attackers & self.occupied_by(!side)).is_empty() {
+                break;
+            }
+            depth += 1;
+            // Gain for `side` if it stops after this capture
+            gain[depth] = value(piece_type) - gain[depth - 1];
+            piece_type = attacker;
+            if attacker == PieceType::Pawn && promotes {
+                gain[depth] += value(PieceType::Queen) - value(PieceType::Pawn);
+                piece_type = PieceType::Queen;
+            }
+            occupied.remove(sq);
+            side = !side;
+        }
+        // Each side only carries on with the exchange if that is better for it
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    // Whether `mv` wins at least `threshold` by static exchange, such as
+    // `see_ge(mv, 0)` for captures which don't lose material
+    pub fn see_ge(&self, mv: Move, threshold: i32) -> bool {
+        self.see(mv) >= threshold
+    }
+
+    // Pieces of both colors attacking `sq`, as if only `occupied` was
+    // occupied, so sliders behind pieces already traded off are found
+    fn attackers_through(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        let queens = self.pieces(PieceType::Queen);
+        let straight = self.pieces(PieceType::Rook) | queens;
+        let diagonal = self.pieces(PieceType::Bishop) | queens;
+        let pawns = self.pieces(PieceType::Pawn);
+        (attacks::pawn_attacks(sq, Color::Black) & pawns & self.occupied_by(Color::White))
+            | (attacks::pawn_attacks(sq, Color::White) & pawns & self.occupied_by(Color::Black))
+            | (attacks::knight_attacks(sq) & self.pieces(PieceType::Knight))
+            | (attacks::king_attacks(sq) & self.pieces(PieceType::King))
+            | (attacks::rook_attacks(sq, occupied) & straight)
+            | (attacks::bishop_attacks(sq, occupied) & diagonal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Square::*;
+
+    fn see(fen: &str, mv: Move) -> i32 {
+        Board::from_fen(fen).unwrap().see(mv)
+    }
+
+    #[test]
+    fn test_see_simple() {
+        // Undefended pawn
+        assert_eq!(
+            see("4k3/8/8/4p3/8/8/8/4RK2 w - - 0 1", Move::normal(E1, E5)),
+            100
+        );
+        // Pawn takes a knight defended by a pawn
+        let fen = "4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(D4, E5)), 200);
+        // Queen takes a pawn defended by a pawn
+        let fen = "4k3/8/3p4/4p3/8/8/8/4QK2 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E1, E5)), -800);
+        // Quiet moves onto attacked squares lose the piece
+        let fen = "4k3/8/3p4/8/8/8/8/4QK2 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E1, E5)), -900);
+        assert_eq!(see(fen, Move::normal(E1, E4)), 0);
+    }
+
+    #[test]
+    fn test_see_x_rays() {
+        // The rook behind backs up the first one
+        let fen = "4r1k1/8/8/4p3/8/8/4R3/4R1K1 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E2, E5)), 100);
+        // Without it the rook is lost for a pawn
+        let fen = "4r1k1/8/8/4p3/8/8/4R3/6K1 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E2, E5)), -400);
+        // A queen behind a bishop on the diagonal
+        let fen = "4k3/3n4/8/4p3/3B4/2Q5/8/4K3 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(D4, E5)), 100);
+        let fen = "4k3/3n4/8/4p3/3B4/8/8/4K3 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(D4, E5)), -200);
+    }
+
+    #[test]
+    fn test_see_special_moves() {
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        assert_eq!(see(fen, Move::new(E5, D6, MoveType::EnPassant)), 100);
+        // Promoting while capturing a defended knight
+        let fen = "1nr3k1/P7/8/8/8/8/8/6K1 w - - 0 1";
+        let promo = Move::new(A7, B8, MoveType::Promotion(PieceType::Queen));
+        assert_eq!(see(fen, promo), 200);
+        let fen = "1n4k1/P7/8/8/8/8/8/6K1 w - - 0 1";
+        assert_eq!(see(fen, promo), 1100);
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1";
+        assert_eq!(see(fen, Move::new(E1, G1, MoveType::Castle)), 0);
+    }
+
+    #[test]
+    fn test_see_king_recapture() {
+        // The king takes back the rook
+        let fen = "8/8/3k4/4p3/8/8/8/4R1K1 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E1, E5)), -400);
+        // Unless the square is covered
+        let fen = "8/8/3k4/4p3/8/8/1B6/4R1K1 w - - 0 1";
+        assert_eq!(see(fen, Move::normal(E1, E5)), 100);
+    }
+
+    #[test]
+    fn test_see_ge() {
+        let board = Board::from_fen("4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1").unwrap();
+        let capture = Move::normal(D4, E5);
+        assert!(board.see_ge(capture, 0));
+        assert!(board.see_ge(capture, 200));
+        assert!(!board.see_ge(capture, 201));
+        assert!(board.see_ge(Move::normal(E1, E2), 0));
+    }
+}
@@ -0,0 +1,301 @@
+use std::mem;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+use crate::{Move, Score};
+
+// Ages wrap around after this many searches
+const AGE_COUNT: u8 = 64;
+// Largest node count a perft entry can hold
+const MAX_PERFT_NODES: u64 = (1 << 48) - 1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    // Score is exact, from a node where a move raised alpha without reaching beta
+    Exact,
+    // Score is at least this much, from a beta cutoff
+    Lower,
+    // Score is at most this much, since no move raised alpha
+    Upper,
+}
+
+// What the search learned about a position
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TtEntry {
+    pub mv: Option<Move>,
+    pub score: Score,
+    pub depth: u8,
+    pub bound: Bound,
+}
+
+// The key is stored xored with the data, so an entry torn by two threads
+// writing at once fails to match instead of returning mixed up data. Data is
+// laid out as depth in bits 0-7, age in bits 8-13, bound in bits 14-15 and the
+// payload in the upper 48 bits. A bound of zero marks an empty slot.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+// The first slot keeps the deepest recent entry and the second takes the rest
+#[derive(Default)]
+struct Bucket {
+    slots: [Slot; 2],
+}
+
+// Fixed-size hash table of search results, shared between threads by reference.
+// Perft results can be cached in it too, but shouldn't share a table with
+// search entries.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let data = self.data.load(Ordering::Relaxed);
+        let key = self.key.load(Ordering::Relaxed) ^ data;
+        (key, data)
+    }
+
+    fn save(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+fn data_depth(data: u64) -> u8 {
+    data as u8
+}
+
+fn data_age(data: u64) -> u8 {
+    (data >> 8) as u8 & (AGE_COUNT - 1)
+}
+
+fn data_bound(data: u64) -> Option<Bound> {
+    match (data >> 14) & 3 {
+        1 => Some(Bound::Exact),
+        2 => Some(Bound::Lower),
+        3 => Some(Bound::Upper),
+        _ => None,
+    }
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    // Table taking up about `size_mb` megabytes, with at least one bucket
+    pub fn new(size_mb: usize) -> Self {
+        let mut tt = TranspositionTable {
+            buckets: Vec::new(),
+            age: AtomicU8::new(0),
+        };
+        tt.resize(size_mb);
+        tt
+    }
+
+    // Reallocates the table, dropping every entry
+    pub fn resize(&mut self, size_mb: usize) {
+        let len = (size_mb * 1024 * 1024 / mem::size_of::<Bucket>()).max(1);
+        self.buckets = (0..len).map(|_| Bucket::default()).collect();
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self
+            .buckets
+            .iter_mut()
+            .flat_map(|bucket| bucket.slots.iter_mut())
+        {
+            *slot = Slot::default();
+        }
+        *self.age.get_mut() = 0;
+    }
+
+    pub fn size_mb(&self) -> usize {
+        self.buckets.len() * mem::size_of::<Bucket>() / (1024 * 1024)
+    }
+
+    // Marks entries stored so far as old, so they are replaced first
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) % AGE_COUNT, Ordering::Relaxed);
+    }
+
+    // Permille of the table used by the current search, from a sample of it
+    pub fn hashfull(&self) -> u16 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = &self.buckets[..self.buckets.len().min(500)];
+        let used = sample
+            .iter()
+            .flat_map(|bucket| bucket.slots.iter())
+            .filter(|slot| {
+                let (_, data) = slot.load();
+                data_bound(data).is_some() && data_age(data) == age
+            })
+            .count();
+        (used * 1000 / (sample.len() * 2)) as u16
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        let data = self.probe_data(key)?;
+        let payload = data >> 16;
+        Some(TtEntry {
+            mv: Move::unpack(payload as u16),
+            score: Score(i32::from((payload >> 16) as u16 as i16)),
+            depth: data_depth(data),
+            bound: data_bound(data)?,
+        })
+    }
+
+    pub fn store(&self, key: u64, mv: Option<Move>, score: Score, depth: u8, bound: Bound) {
+        let score = score.0.clamp(i16::MIN.into(), i16::MAX.into()) as i16;
+        let payload = u64::from(mv.map_or(0, Move::pack)) | u64::from(score as u16) << 16;
+        self.store_data(key, depth, bound, payload);
+    }
+
+    // Node count of a perft subtree to `depth` plies
+    pub(crate) fn probe_perft(&self, key: u64, depth: u8) -> Option<u64> {
+        self.probe_data(key)
+            .filter(|&data| data_depth(data) == depth)
+            .map(|data| data >> 16)
+    }
+
+    pub(crate) fn store_perft(&self, key: u64, depth: u8, nodes: u64) {
+        if nodes <= MAX_PERFT_NODES {
+            self.store_data(key, depth, Bound::Exact, nodes);
+        }
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        // Maps the key onto the table evenly without needing a power of two size
+        let index = (u128::from(key) * self.buckets.len() as u128) >> 64;
+        &self.buckets[index as usize]
+    }
+
+    fn probe_data(&self, key: u64) -> Option<u64> {
+        self.bucket(key).slots.iter().find_map(|slot| {
+            let (slot_key, data) = slot.load();
+            if slot_key == key && data_bound(data).is_some() {
+                Some(data)
+            } else {
+                None
+            }
+        })
+    }
+
+    fn store_data(&self, key: u64, depth: u8, bound: Bound, payload: u64) {
+        let age = self.age.load(Ordering::Relaxed);
+        let bound = match bound {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        };
+        let data = u64::from(depth) | u64::from(age) << 8 | bound << 14 | payload << 16;
+
+        let [deep, always] = &self.bucket(key).slots;
+        let (deep_key, deep_data) = deep.load();
+        let replace_deep = data_bound(deep_data).is_none()
+            || deep_key == key
+            || depth >= data_depth(deep_data)
+            || data_age(deep_data) != age;
+        if replace_deep {
+            deep.save(key, data);
+        } else {
+            always.save(key, data);
+        }
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SIZE_MB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MoveType, Square};
+    use Square::*;
+
+    #[test]
+    fn test_store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.probe(42), None);
+        let mv = Move::new(E2, E4, MoveType::DoublePush);
+        tt.store(42, Some(mv), Score(-123), 5, Bound::Lower);
+        assert_eq!(
+            tt.probe(42),
+            Some(TtEntry {
+                mv: Some(mv),
+                score: Score(-123),
+                depth: 5,
+                bound: Bound::Lower,
+            })
+        );
+        tt.store(7, None, Score::mated_in(3), 0, Bound::Exact);
+        let entry = tt.probe(7).unwrap();
+        assert_eq!(entry.mv, None);
+        assert_eq!(entry.score, Score::mated_in(3));
+        assert_eq!(tt.probe(43), None);
+    }
+
+    #[test]
+    fn test_replacement() {
+        // A single bucket, so every key collides
+        let mut tt = TranspositionTable::new(0);
+        assert_eq!(tt.buckets.len(), 1);
+        tt.store(1, None, Score(1), 8, Bound::Exact);
+        // Shallower entries go to the always-replace slot
+        tt.store(2, None, Score(2), 3, Bound::Exact);
+        tt.store(3, None, Score(3), 4, Bound::Exact);
+        assert!(tt.probe(1).is_some());
+        assert!(tt.probe(2).is_none());
+        assert!(tt.probe(3).is_some());
+        // Deeper ones take over the depth-preferred slot
+        tt.store(4, None, Score(4), 9, Bound::Exact);
+        assert!(tt.probe(1).is_none());
+        assert_eq!(tt.probe(4).unwrap().depth, 9);
+        // Entries from an older search are replaced regardless of depth
+        tt.new_search();
+        tt.store(5, None, Score(5), 1, Bound::Upper);
+        assert!(tt.probe(4).is_none());
+        assert!(tt.probe(5).is_some());
+
+        tt.clear();
+        assert!(tt.probe(3).is_none());
+        assert!(tt.probe(5).is_none());
+    }
+
+    #[test]
+    fn test_hashfull_and_resize() {
+        let mut tt = TranspositionTable::new(1);
+        assert_eq!(tt.size_mb(), 1);
+        assert_eq!(tt.hashfull(), 0);
+        for key in 0..1000u64 {
+            tt.store(
+                key.wrapping_mul(0x9e37_79b9_7f4a_7c15),
+                None,
+                Score(0),
+                1,
+                Bound::Exact,
+            );
+        }
+        assert!(tt.hashfull() > 0);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+        tt.resize(2);
+        assert_eq!(tt.size_mb(), 2);
+        assert_eq!(tt.probe(0), None);
+    }
+
+    #[test]
+    fn test_perft_entries() {
+        let tt = TranspositionTable::new(1);
+        tt.store_perft(99, 4, 197_281);
+        assert_eq!(tt.probe_perft(99, 4), Some(197_281));
+        assert_eq!(tt.probe_perft(99, 3), None);
+        tt.store_perft(98, 4, MAX_PERFT_NODES + 1);
+        assert_eq!(tt.probe_perft(98, 4), None);
+    }
+}
@@ -0,0 +1,149 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Board, Move, MoveType, PieceType};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum UciMoveError {
+    // Not shaped like `e2e4` or `e7e8q`
+    InvalidSyntax,
+    // Well formed, but no legal move matches
+    IllegalMove,
+}
+
+impl Board {
+    // Resolves a move in UCI coordinate notation, like `e2e4` or `e7e8q`,
+    // against the legal moves, so castling, en passant and double pushes get
+    // their proper `MoveType`. Castling may also be written as the king taking
+    // its own rook, like `e1h1`, as in Chess960.
+    pub fn parse_uci_move(&self, text: &str) -> Result<Move, UciMoveError> {
+        let parsed = Move::from_str(text).map_err(|_| UciMoveError::InvalidSyntax)?;
+        let promo = promotion(parsed);
+        self.gen_legal_moves()
+            .into_iter()
+            .find(|&mv| {
+                let to_matches = mv.to() == parsed.to()
+                    || (mv.move_type() == MoveType::Castle
+                        && Self::castle_rook_squares(mv).0 == parsed.to());
+                mv.from() == parsed.from() && to_matches && promotion(mv) == promo
+            })
+            .ok_or(UciMoveError::IllegalMove)
+    }
+
+    // UCI coordinate notation of a move, the inverse of `parse_uci_move`.
+    // Castling is written as the king taking its own rook in Chess960 mode.
+    pub fn move_to_uci(&self, mv: Move) -> String {
+        match mv.move_type() {
+            MoveType::Promotion(promo) => format!("{}{}{}", mv.from(), mv.to(), promo),
+            MoveType::Castle if self.chess960 => {
+                format!("{}{}", mv.from(), Self::castle_rook_squares(mv).0)
+            }
+            _ => format!("{}{}", mv.from(), mv.to()),
+        }
+    }
+}
+
+fn promotion(mv: Move) -> Option<PieceType> {
+    match mv.move_type() {
+        MoveType::Promotion(promo) => Some(promo),
+        _ => None,
+    }
+}
+
+impl Error for UciMoveError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
+
+impl fmt::Display for UciMoveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match self {
+            UciMoveError::InvalidSyntax => "Invalid UCI move syntax",
+            UciMoveError::IllegalMove => "No legal move matches UCI move",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Square::*;
+
+    #[test]
+    fn test_parse_uci_move() {
+        let board = Board::default();
+        assert_eq!(
+            board.parse_uci_move("e2e4"),
+            Ok(Move::new(E2, E4, MoveType::DoublePush))
+        );
+        assert_eq!(board.parse_uci_move("g1f3"), Ok(Move::normal(G1, F3)));
+        assert_eq!(board.parse_uci_move("e2e5"), Err(UciMoveError::IllegalMove));
+        assert_eq!(board.parse_uci_move("e2"), Err(UciMoveError::InvalidSyntax));
+        assert_eq!(
+            board.parse_uci_move("e2e4x"),
+            Err(UciMoveError::InvalidSyntax)
+        );
+
+        let board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let short = Move::new(E1, G1, MoveType::Castle);
+        let long = Move::new(E1, C1, MoveType::Castle);
+        assert_eq!(board.parse_uci_move("e1g1"), Ok(short));
+        assert_eq!(board.parse_uci_move("e1h1"), Ok(short));
+        assert_eq!(board.parse_uci_move("e1c1"), Ok(long));
+        assert_eq!(board.parse_uci_move("e1a1"), Ok(long));
+        assert_eq!(
+            board.parse_uci_move("e5d6"),
+            Ok(Move::new(E5, D6, MoveType::EnPassant))
+        );
+        assert_eq!(
+            board.parse_uci_move("b7a8n"),
+            Ok(Move::new(B7, A8, MoveType::Promotion(PieceType::Knight)))
+        );
+        // Promotions need their piece
+        assert_eq!(board.parse_uci_move("b7b8"), Err(UciMoveError::IllegalMove));
+        assert_eq!(
+            board.parse_uci_move("b7a8k"),
+            Err(UciMoveError::IllegalMove)
+        );
+        assert_eq!(
+            board.parse_uci_move("e1f1q"),
+            Err(UciMoveError::IllegalMove)
+        );
+
+        // Under-promotions, which the search may skip, are always legal input
+        assert_eq!(
+            board.parse_uci_move("b7a8r"),
+            Ok(Move::new(B7, A8, MoveType::Promotion(PieceType::Rook)))
+        );
+        assert_eq!(
+            board.parse_uci_move("b7b8b"),
+            Ok(Move::new(B7, B8, MoveType::Promotion(PieceType::Bishop)))
+        );
+    }
+
+    #[test]
+    fn test_move_to_uci() {
+        let mut board = Board::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let short = Move::new(E1, G1, MoveType::Castle);
+        let long = Move::new(E1, C1, MoveType::Castle);
+        let promo = Move::new(B7, A8, MoveType::Promotion(PieceType::Queen));
+        assert_eq!(board.move_to_uci(short), "e1g1");
+        assert_eq!(board.move_to_uci(long), "e1c1");
+        assert_eq!(board.move_to_uci(promo), "b7a8q");
+        assert_eq!(
+            board.move_to_uci(Move::new(E5, D6, MoveType::EnPassant)),
+            "e5d6"
+        );
+
+        board.chess960 = true;
+        assert_eq!(board.move_to_uci(short), "e1h1");
+        assert_eq!(board.move_to_uci(long), "e1a1");
+        assert_eq!(board.move_to_uci(promo), "b7a8q");
+        for mv in board.gen_legal_moves() {
+            assert_eq!(board.parse_uci_move(&board.move_to_uci(mv)), Ok(mv));
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use crate::attacks;
+use crate::{Bitboard, Board, BoardPiece, Color, FenError, File, PieceType, Rank, Square};
+
+// Something making a position impossible to reach in a real game, found by
+// `Board::validate`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PositionIssue {
+    // A side doesn't have exactly one king, with how many it has
+    KingCount(Color, u32),
+    PawnOnBackRank(Square),
+    // The side which just moved left its own king in check
+    OpponentInCheck,
+    // A castling right without the king and rook on their starting squares
+    BogusCastling { color: Color, king_side: bool },
+    // An en passant square without a pawn which could just have moved past it
+    BogusEnPassant(Square),
+    TooManyPawns(Color),
+    // More pieces than the missing pawns could have promoted to
+    TooManyPieces(Color),
+    // Checks that no single move could have given
+    ImpossibleCheck,
+}
+
+impl Board {
+    // Checks the position could come up in a game, unlike `from_fen` which
+    // accepts anything it can represent. Only clear cases are caught, so some
+    // unreachable positions still pass.
+    pub fn validate(&self) -> Result<(), Vec<PositionIssue>> {
+        let mut issues = Vec::new();
+        for &color in [Color::White, Color::Black].iter() {
+            let kings = self.pieces_of(PieceType::King, color).count();
+            if kings != 1 {
+                issues.push(PositionIssue::KingCount(color, kings));
+            }
+        }
+        let back_ranks = Bitboard::rank(Rank::R1) | Bitboard::rank(Rank::R8);
+        for sq in self.pieces(PieceType::Pawn) & back_ranks {
+            issues.push(PositionIssue::PawnOnBackRank(sq));
+        }
+        if let Some(king_sq) = self.king_square(!self.turn) {
+            if self.is_attacked(king_sq, self.turn) {
+                issues.push(PositionIssue::OpponentInCheck);
+            }
+        }
+        issues.extend(self.castling_issues());
+        if let Some(sq) = self.ep_square() {
+            if !self.ep_is_plausible(sq) {
+                issues.push(PositionIssue::BogusEnPassant(sq));
+            }
+        }
+        for &color in [Color::White, Color::Black].iter() {
+            let pawns = self.pieces_of(PieceType::Pawn, color).count();
+            if pawns > 8 {
+                issues.push(PositionIssue::TooManyPawns(color));
+            } else if self.promoted_count(color) > 8 - pawns {
+                issues.push(PositionIssue::TooManyPieces(color));
+            }
+        }
+        if self.has_impossible_check() {
+            issues.push(PositionIssue::ImpossibleCheck);
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(issues)
+        }
+    }
+
+    // Fixes what can be fixed by dropping state the position claims, which
+    // are bogus castling rights and en passant squares, then validates it
+    pub fn repair(&mut self) -> Result<(), Vec<PositionIssue>> {
+        for issue in self.castling_issues() {
+            if let PositionIssue::BogusCastling { color, king_side } = issue {
+                let file = if king_side { File::H } else { File::A };
+                let rook_sq = Square::from((back_rank(color), file));
+                self.castle_rights.remove_square(rook_sq);
+            }
+        }
+        if let Some(sq) = self.ep_square() {
+            if !self.ep_is_plausible(sq) {
+                self.ep_file = None;
+            }
+        }
+        self.refresh_hash();
+        self.validate()
+    }
+
+    // Like `from_fen`, but rejects positions failing `validate`
+    pub fn from_fen_strict(fen: &str) -> Result<Self, FenError> {
+        let board = Self::from_fen(fen)?;
+        board.validate().map_err(FenError::IllegalPosition)?;
+        Ok(board)
+    }
+
+    // Like `from_fen_strict`, but first repairs what it can
+    pub fn from_fen_lenient(fen: &str) -> Result<Self, FenError> {
+        let mut board = Self::from_fen(fen)?;
+        board.repair().map_err(FenError::IllegalPosition)?;
+        Ok(board)
+    }
+
+    fn castling_issues(&self) -> Vec<PositionIssue> {
+        let mut issues = Vec::new();
+        for &color in [Color::White, Color::Black].iter() {
+            let rank = back_rank(color);
+            let has = |piece_type, file| {
+                self.pieces_of(piece_type, color)
+                    .contains(Square::from((rank, file)))
+            };
+            let king_home = has(PieceType::King, File::E);
+            let rights = [
+                (true, self.castle_rights.king_side(color), File::H),
+                (false, self.castle_rights.queen_side(color), File::A),
+            ];
+            for &(king_side, right, rook_file) in rights.iter() {
+                if right && !(king_home && has(PieceType::Rook, rook_file)) {
+                    issues.push(PositionIssue::BogusCastling { color, king_side });
+                }
+            }
+        }
+        issues
+    }
+
+    // Whether a pawn of the side not to move could just have moved two
+    // squares, passing `ep_sq`
+    fn ep_is_plausible(&self, ep_sq: Square) -> bool {
+        let pushed = ep_sq.down(self.turn).unwrap();
+        let origin = ep_sq.up(self.turn).unwrap();
+        self.pieces_of(PieceType::Pawn, !self.turn).contains(pushed)
+            && self.piece_at(ep_sq) == BoardPiece::Empty
+            && self.piece_at(origin) == BoardPiece::Empty
+    }
+
+    // Pieces of `color` beyond those of the starting position, which must
+    // have been promoted
+    fn promoted_count(&self, color: Color) -> u32 {
+        let extra = |piece_type, start: u32| {
+            self.pieces_of(piece_type, color)
+                .count()
+                .saturating_sub(start)
+        };
+        extra(PieceType::Queen, 1)
+            + extra(PieceType::Rook, 2)
+            + extra(PieceType::Bishop, 2)
+            + extra(PieceType::Knight, 2)
+    }
+
+    // Whether the side to move is in check in a way the last move couldn't
+    // have caused: by more than two pieces, by two pieces neither of which
+    // can give a discovered check, or by a piece a double pushed pawn didn't
+    // uncover
+    fn has_impossible_check(&self) -> bool {
+        let checkers = self.checkers();
+        if checkers.count() > 2 {
+            return true;
+        }
+        let sliders = self.pieces(PieceType::Bishop)
+            | self.pieces(PieceType::Rook)
+            | self.pieces(PieceType::Queen);
+        if checkers.count() == 2 && (checkers & sliders).is_empty() {
+            return true;
+        }
+        let (king_sq, ep_sq) = match (self.king_square(self.turn), self.ep_square()) {
+            (Some(king_sq), Some(ep_sq)) if self.ep_is_plausible(ep_sq) => (king_sq, ep_sq),
+            _ => return false,
+        };
+        // Each check must come from the pawn itself, or through its origin
+        let pushed = ep_sq.down(self.turn).unwrap();
+        let origin = ep_sq.up(self.turn).unwrap();
+        let mut before = self.occupied();
+        before.remove(pushed);
+        before.insert(origin);
+        checkers
+            .iter()
+            .filter(|&sq| sq != pushed)
+            .any(|sq| self.attacks_from(sq, before).contains(king_sq))
+    }
+
+    // Squares attacked by the piece on `sq`, as if only `occupied` was occupied
+    fn attacks_from(&self, sq: Square, occupied: Bitboard) -> Bitboard {
+        match self.piece_at(sq) {
+            BoardPiece::Piece(piece) => match piece.piece_type() {
+                PieceType::Pawn => attacks::pawn_attacks(sq, piece.color()),
+                PieceType::Knight => attacks::knight_attacks(sq),
+                PieceType::Bishop => attacks::bishop_attacks(sq, occupied),
+                PieceType::Rook => attacks::rook_attacks(sq, occupied),
+                PieceType::Queen => attacks::queen_attacks(sq, occupied),
+                PieceType::King => attacks::king_attacks(sq),
+            },
+            BoardPiece::Empty => Bitboard::EMPTY,
+        }
+    }
+}
+
+fn back_rank(color: Color) -> Rank {
+    match color {
+        Color::White => Rank::R1,
+        Color::Black => Rank::R8,
+    }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Color::White => "white",
+        Color::Black => "black",
+    }
+}
+
+impl fmt::Display for PositionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PositionIssue::KingCount(color, count) => {
+                write!(f, "{} has {} kings", color_name(color), count)
+            }
+            PositionIssue::PawnOnBackRank(sq) => write!(f, "pawn on back rank at {}", sq),
+            PositionIssue::OpponentInCheck => write!(f, "side not to move is in check"),
+            PositionIssue::BogusCastling { color, king_side } => write!(
+                f,
+                "{} can't castle {} side",
+                color_name(color),
+                if king_side { "king" } else { "queen" }
+            ),
+            PositionIssue::BogusEnPassant(sq) => {
+                write!(f, "no pawn could have just passed {}", sq)
+            }
+            PositionIssue::TooManyPawns(color) => {
+                write!(f, "{} has too many pawns", color_name(color))
+            }
+            PositionIssue::TooManyPieces(color) => {
+                write!(f, "{} has too many promoted pieces", color_name(color))
+            }
+            PositionIssue::ImpossibleCheck => write!(f, "impossible check"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(fen: &str) -> Vec<PositionIssue> {
+        Board::from_fen(fen)
+            .unwrap()
+            .validate()
+            .err()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_validate_legal() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1pppp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq d6 0 3",
+            // Promoted queens replacing pawns
+            "QQQQ1k2/8/8/8/8/8/4PPPP/4K3 b - - 0 1",
+            // Double check by a knight and a discovering rook
+            "4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1",
+            // Check from the pawn which just moved two squares
+            "8/8/8/4k3/3P4/8/8/4K3 b - d3 0 1",
+            // Check discovered by the pawn leaving its origin
+            "8/8/8/8/2P5/8/Q2k4/7K b - c3 0 1",
+        ]
+        .iter()
+        {
+            assert_eq!(issues(fen), vec![], "{}", fen);
+        }
+    }
+
+    #[test]
+    fn test_validate_issues() {
+        assert_eq!(
+            issues("8/8/8/8/8/8/8/4K3 w - - 0 1"),
+            vec![PositionIssue::KingCount(Color::Black, 0)]
+        );
+        assert_eq!(
+            issues("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"),
+            vec![PositionIssue::KingCount(Color::White, 2)]
+        );
+        assert_eq!(
+            issues("3pk3/8/8/8/8/8/8/4K2P w - - 0 1"),
+            vec![
+                PositionIssue::PawnOnBackRank(Square::H1),
+                PositionIssue::PawnOnBackRank(Square::D8)
+            ]
+        );
+        assert_eq!(
+            issues("4k3/8/8/8/8/8/8/4K2r b - - 0 1"),
+            vec![PositionIssue::OpponentInCheck]
+        );
+        assert_eq!(
+            issues("r3k3/8/8/8/8/8/8/4K2R w KQkq - 0 1"),
+            vec![
+                PositionIssue::BogusCastling {
+                    color: Color::White,
+                    king_side: false
+                },
+                PositionIssue::BogusCastling {
+                    color: Color::Black,
+                    king_side: true
+                }
+            ]
+        );
+        assert_eq!(
+            issues("4k3/8/8/8/8/8/8/4K3 w - d6 0 1"),
+            vec![PositionIssue::BogusEnPassant(Square::D6)]
+        );
+        assert_eq!(
+            issues("4k3/pppppppp/p7/8/8/8/8/4K3 w - - 0 1"),
+            vec![PositionIssue::TooManyPawns(Color::Black)]
+        );
+        assert_eq!(
+            issues("4k3/8/8/8/8/NNN5/PPPPPPPP/4K3 w - - 0 1"),
+            vec![PositionIssue::TooManyPieces(Color::White)]
+        );
+        // Two knights can't both give check
+        assert_eq!(
+            issues("4k3/8/3N1N2/8/8/8/8/4K3 b - - 0 1"),
+            vec![PositionIssue::ImpossibleCheck]
+        );
+        // The rook's check isn't explained by the double push
+        assert_eq!(
+            issues("4k3/8/8/8/3P4/8/8/4RK2 b - d3 0 1"),
+            vec![PositionIssue::ImpossibleCheck]
+        );
+    }
+
+    #[test]
+    fn test_repair() {
+        let fen = "r3k3/8/8/8/8/8/8/4K2R w KQkq d6 0 1";
+        assert!(Board::from_fen_strict(fen).is_err());
+        let board = Board::from_fen_lenient(fen).unwrap();
+        assert_eq!(board.to_fen(), "r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1");
+        assert_eq!(board.hash(), board.compute_hash());
+
+        // Missing kings can't be repaired
+        let err = Board::from_fen_lenient("8/8/8/8/8/8/8/4K3 w - - 0 1").unwrap_err();
+        assert_eq!(
+            err,
+            FenError::IllegalPosition(vec![PositionIssue::KingCount(Color::Black, 0)])
+        );
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert!(Board::from_fen_strict(start).is_ok());
+    }
+}
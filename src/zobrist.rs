@@ -0,0 +1,73 @@
+use crate::{CastlingRights, File, SidePiece, Square};
+
+static PIECE_KEYS: [[u64; 64]; 12] = piece_keys();
+static CASTLE_KEYS: [u64; 16] = keys::<16>(12 * 64);
+static EP_KEYS: [u64; 8] = keys::<8>(12 * 64 + 16);
+const BLACK_TO_MOVE_KEY: u64 = splitmix64(12 * 64 + 16 + 8);
+
+// Deterministic pseudo-random number for each index, so keys are fixed at
+// compile time and hashes are stable across runs.
+const fn splitmix64(index: u64) -> u64 {
+    let mut z = index.wrapping_add(1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn keys<const N: usize>(start: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut i = 0;
+    while i < N {
+        keys[i] = splitmix64(start + i as u64);
+        i += 1;
+    }
+    keys
+}
+
+const fn piece_keys() -> [[u64; 64]; 12] {
+    let mut keys = [[0; 64]; 12];
+    let mut piece = 0;
+    while piece < 12 {
+        let mut sq = 0;
+        while sq < 64 {
+            keys[piece][sq] = splitmix64((64 * piece + sq) as u64);
+            sq += 1;
+        }
+        piece += 1;
+    }
+    keys
+}
+
+pub fn piece(piece: SidePiece, sq: Square) -> u64 {
+    PIECE_KEYS[piece as usize][sq as usize]
+}
+
+pub fn castling(rights: CastlingRights) -> u64 {
+    CASTLE_KEYS[rights.index()]
+}
+
+pub fn ep_file(file: File) -> u64 {
+    EP_KEYS[file as usize]
+}
+
+pub fn black_to_move() -> u64 {
+    BLACK_TO_MOVE_KEY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_keys_unique() {
+        let mut seen = HashSet::new();
+        for row in PIECE_KEYS.iter() {
+            assert!(row.iter().all(|&key| seen.insert(key)));
+        }
+        assert!(CASTLE_KEYS.iter().all(|&key| seen.insert(key)));
+        assert!(EP_KEYS.iter().all(|&key| seen.insert(key)));
+        assert!(seen.insert(BLACK_TO_MOVE_KEY));
+        assert!(!seen.contains(&0));
+    }
+}
@@ -10,23 +10,38 @@ fn test_move_invariance_default() {
     eprintln!("Moves tested: {}", n);
 }
 
+#[test]
+fn test_move_invariance_kiwipete() {
+    let mut game =
+        Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+            .unwrap();
+    test_make_undo_move(&mut game, 3);
+}
+
 fn test_make_undo_move(game: &mut Game, max_depth: u8) -> usize {
     if max_depth == 0 {
         return 1;
     }
-    let og_fen = game.board.to_fen();
+    let og_hash = game.board.hash();
     let mut n = 1;
     for mv in game.board.gen_pseudo_moves() {
         game.make_move(mv);
+        // Incremental hash must match hashing from scratch
+        assert_eq!(
+            game.board.hash(),
+            game.board.compute_hash(),
+            "Hash out of sync:\n{:?}\n{:?}",
+            game,
+            mv,
+        );
         // Recurse to check sub-board configuration
         n += test_make_undo_move(game, max_depth - 1);
         game.undo_move();
-        // TODO: Consider to clone or hash instead of FEN string
         // Assert board is same as before applying/undoing move
         assert_eq!(
-            og_fen,
-            game.board.to_fen(),
-            "FEN doesn't match:\n{:?}\n{:?}",
+            og_hash,
+            game.board.hash(),
+            "Hash doesn't match:\n{:?}\n{:?}",
             game,
             mv,
         );
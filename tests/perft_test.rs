@@ -0,0 +1,116 @@
+extern crate qchess;
+
+use qchess::*;
+
+// Positions and node counts from https://www.chessprogramming.org/Perft_Results
+const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+const KIWIPETE_FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3_FEN: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4_FEN: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED_FEN: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5_FEN: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6_FEN: &str =
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn check_perft(fen: &str, expected: &[u64]) {
+    let mut board = Board::from_fen(fen).unwrap();
+    for (depth, &nodes) in (1..).zip(expected.iter()) {
+        assert_eq!(
+            board.perft(depth),
+            nodes,
+            "Perft mismatch at depth {} for {}",
+            depth,
+            fen,
+        );
+    }
+    // Perft must leave the board untouched
+    assert_eq!(board.to_fen(), fen);
+}
+
+#[test]
+fn test_perft_start() {
+    check_perft(START_FEN, &[20, 400, 8_902, 197_281]);
+}
+
+#[test]
+fn test_perft_kiwipete() {
+    check_perft(KIWIPETE_FEN, &[48, 2_039, 97_862]);
+}
+
+#[test]
+fn test_perft_position_3() {
+    check_perft(POSITION_3_FEN, &[14, 191, 2_812, 43_238]);
+}
+
+#[test]
+fn test_perft_position_4() {
+    check_perft(POSITION_4_FEN, &[6, 264, 9_467]);
+    check_perft(POSITION_4_MIRRORED_FEN, &[6, 264, 9_467]);
+}
+
+#[test]
+fn test_perft_position_5() {
+    check_perft(POSITION_5_FEN, &[44, 1_486, 62_379]);
+}
+
+#[test]
+fn test_perft_position_6() {
+    check_perft(POSITION_6_FEN, &[46, 2_079, 89_890]);
+}
+
+#[test]
+#[ignore]
+fn test_perft_deep() {
+    check_perft(START_FEN, &[20, 400, 8_902, 197_281, 4_865_609]);
+    check_perft(POSITION_3_FEN, &[14, 191, 2_812, 43_238, 674_624]);
+    check_perft(POSITION_6_FEN, &[46, 2_079, 89_890, 3_894_594]);
+    check_perft(KIWIPETE_FEN, &[48, 2_039, 97_862, 4_085_603]);
+    check_perft(POSITION_4_FEN, &[6, 264, 9_467, 422_333]);
+    check_perft(POSITION_4_MIRRORED_FEN, &[6, 264, 9_467, 422_333]);
+    check_perft(POSITION_5_FEN, &[44, 1_486, 62_379, 2_103_487]);
+}
+
+#[test]
+fn test_perft_cached() {
+    let table = TranspositionTable::new(4);
+    for &fen in [
+        START_FEN,
+        KIWIPETE_FEN,
+        POSITION_3_FEN,
+        POSITION_4_FEN,
+        POSITION_6_FEN,
+    ]
+    .iter()
+    {
+        let mut board = Board::from_fen(fen).unwrap();
+        for depth in 0..=3 {
+            assert_eq!(board.perft_cached(depth, &table), board.perft(depth));
+        }
+        assert_eq!(board.to_fen(), fen);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_perft_cached_deep() {
+    let table = TranspositionTable::new(64);
+    let mut board = Board::from_fen(START_FEN).unwrap();
+    assert_eq!(board.perft_cached(6, &table), 119_060_324);
+    let mut board = Board::from_fen(KIWIPETE_FEN).unwrap();
+    assert_eq!(board.perft_cached(5, &table), 193_690_690);
+}
+
+#[test]
+fn test_perft_divide() {
+    let mut game = Game::default();
+    let divide = game.perft_divide(3);
+    assert_eq!(divide.len(), 20);
+    assert_eq!(divide.iter().map(|&(_, n)| n).sum::<u64>(), 8_902);
+    let e2e4 = Move::new(Square::E2, Square::E4, MoveType::DoublePush);
+    assert_eq!(
+        divide.iter().find(|&&(mv, _)| mv == e2e4),
+        Some(&(e2e4, 600))
+    );
+    assert!(game.perft_divide(0).is_empty());
+}
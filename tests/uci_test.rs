@@ -0,0 +1,117 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+// Runs the UCI binary on a script of commands and returns what it printed
+fn run_uci(script: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_qchess-uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(script.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+// Like `run_uci`, but lets the search started by the script finish before
+// quitting, since quitting stops it
+fn run_uci_search(script: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_qchess-uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(script.as_bytes()).unwrap();
+    let mut output = String::new();
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    while !output.lines().any(|line| line.starts_with("bestmove ")) {
+        assert!(stdout.read_line(&mut output).unwrap() > 0);
+    }
+    stdin.write_all(b"quit\n").unwrap();
+    assert!(child.wait().unwrap().success());
+    output
+}
+
+#[test]
+fn test_uci_handshake() {
+    let output = run_uci("uci\nisready\nquit\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[0].starts_with("id name qchess"));
+    assert!(lines.contains(&"uciok"));
+    assert!(lines
+        .iter()
+        .any(|line| line.starts_with("option name Hash type spin default 16 min 1 max ")));
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false"));
+    assert!(lines.contains(&"option name UnderPromotions type check default true"));
+    assert_eq!(lines.last(), Some(&"readyok"));
+}
+
+#[test]
+fn test_uci_go() {
+    let output = run_uci_search("position startpos moves e2e4 e7e5 g1f3\ngo depth 1\n");
+    assert!(output.lines().any(|line| line.starts_with("bestmove ")));
+    assert!(output
+        .lines()
+        .any(|line| line.starts_with("info depth 1 score cp ")));
+
+    // Mated side has no move
+    let output = run_uci_search("position fen R3k3/8/4K3/8/8/8/8/8 b - - 0 1\ngo movetime 10\n");
+    assert!(output.lines().any(|line| line == "bestmove 0000"));
+}
+
+#[test]
+fn test_uci_infinite_waits_for_stop() {
+    let output = run_uci("ucinewgame\nposition startpos\ngo infinite\nisready\nstop\nquit\n");
+    // Search info can come at any time, so only the order of the rest matters
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.starts_with("info "))
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0], "readyok");
+    assert!(lines[1].starts_with("bestmove "));
+}
+
+#[test]
+fn test_uci_bad_input() {
+    let output = run_uci(
+        "position startpos moves e2e5\nposition fen nonsense\n\
+         setoption name Nope value 1\nfoo\nisready\nquit\n",
+    );
+    assert!(output.contains("info string Illegal move e2e5"));
+    assert!(output.contains("info string Unknown option Nope"));
+    assert!(output.ends_with("readyok\n"));
+}
+
+#[test]
+fn test_uci_hash_options() {
+    let output = run_uci_search(
+        "setoption name Hash value 1\nsetoption name Clear Hash\n\
+         position startpos\ngo depth 3\n",
+    );
+    assert!(!output.contains("Unknown option"));
+    assert!(output
+        .lines()
+        .any(|line| line.starts_with("info depth 3 ") && line.contains(" hashfull ")));
+}
+
+#[test]
+fn test_uci_chess960_castling() {
+    // Castling is the only mate
+    let position = "position fen 5k2/3P3P/3P3P/8/8/8/8/4K2R w K - 0 1\ngo depth 2\n";
+    let output = run_uci_search(position);
+    assert!(output.lines().any(|line| line == "bestmove e1g1"));
+    let output = run_uci_search(&format!(
+        "setoption name UCI_Chess960 value true\n{}",
+        position
+    ));
+    assert!(!output.contains("Unknown option"));
+    assert!(output.lines().any(|line| line == "bestmove e1h1"));
+}
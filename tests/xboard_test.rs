@@ -0,0 +1,156 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+// CECP binary driven one command at a time, since replies arrive asynchronously
+struct Xboard {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Xboard {
+    fn new() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_qchess-xboard"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let mut xboard = Xboard {
+            child,
+            stdin,
+            stdout,
+        };
+        xboard.send("xboard");
+        xboard.send("protover 2");
+        assert!(xboard.read_line().contains("done=1"));
+        xboard.send("accepted usermove");
+        xboard
+    }
+
+    fn send(&mut self, command: &str) {
+        writeln!(self.stdin, "{}", command).unwrap();
+    }
+
+    fn read_line(&mut self) -> String {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+        line.trim_end().to_string()
+    }
+
+    // Sends a ping and collects everything printed before the matching pong
+    fn sync(&mut self) -> Vec<String> {
+        self.send("ping 7");
+        let mut lines = Vec::new();
+        loop {
+            let line = self.read_line();
+            if line == "pong 7" {
+                return lines;
+            }
+            lines.push(line);
+        }
+    }
+}
+
+impl Drop for Xboard {
+    fn drop(&mut self) {
+        self.send("quit");
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+#[test]
+fn test_xboard_engine_replies() {
+    let mut xboard = Xboard::new();
+    xboard.send("new");
+    xboard.send("sd 2");
+    xboard.send("post");
+    xboard.send("usermove e2e4");
+    assert_eq!(xboard.read_line().split(' ').next(), Some("1"));
+    assert_eq!(xboard.read_line().split(' ').next(), Some("2"));
+    assert!(xboard.read_line().starts_with("move "));
+    xboard.send("usermove e2e4");
+    assert_eq!(xboard.sync(), vec!["Illegal move: e2e4"]);
+}
+
+#[test]
+fn test_xboard_force_and_go() {
+    let mut xboard = Xboard::new();
+    xboard.send("force");
+    xboard.send("usermove e2e4");
+    xboard.send("usermove e7e5");
+    xboard.send("undo");
+    assert!(xboard.sync().is_empty());
+    xboard.send("st 0.05");
+    xboard.send("go");
+    assert!(xboard.read_line().starts_with("move "));
+    xboard.send("remove");
+    xboard.send("usermove e7e5");
+    assert_eq!(xboard.sync(), vec!["Illegal move: e7e5"]);
+}
+
+#[test]
+fn test_xboard_setboard_and_result() {
+    let mut xboard = Xboard::new();
+    xboard.send("setboard nonsense");
+    assert_eq!(xboard.sync(), vec!["tellusererror Illegal position"]);
+    xboard.send("setboard 7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+    xboard.send("force");
+    xboard.send("level 40 5 0");
+    xboard.send("st 2");
+    xboard.send("sd 4");
+    xboard.send("usermove a1a8");
+    assert_eq!(xboard.sync(), vec!["1-0 {Checkmate}"]);
+}
+
+#[test]
+fn test_xboard_mate_score() {
+    let mut xboard = Xboard::new();
+    xboard.send("setboard 7k/8/6K1/8/8/8/8/R7 w - - 0 1");
+    xboard.send("sd 2");
+    xboard.send("post");
+    xboard.send("go");
+    // Mate in one move
+    let thinking = xboard.read_line();
+    assert_eq!(thinking.split(' ').nth(1), Some("100001"), "{}", thinking);
+}
+
+#[test]
+fn test_xboard_memory() {
+    let mut xboard = Xboard::new();
+    xboard.send("memory 4");
+    assert!(xboard.sync().is_empty());
+    xboard.send("memory 0");
+    assert_eq!(xboard.sync(), vec!["Error (bad memory size): 0"]);
+}
+
+#[test]
+fn test_xboard_unknown_commands() {
+    let mut xboard = Xboard::new();
+    xboard.send("force");
+    xboard.send("name Someone");
+    xboard.send("draw");
+    xboard.send("bogus 1 2");
+    xboard.send("e2e4");
+    assert_eq!(
+        xboard.sync(),
+        vec![
+            "Error (unknown command): bogus",
+            "Error (unknown command): e2e4"
+        ]
+    );
+
+    // Moves only arrive bare if `usermove=1` was rejected
+    xboard.send("rejected usermove");
+    xboard.send("e2e4");
+    xboard.send("e2e4");
+    assert_eq!(xboard.sync(), vec!["Illegal move: e2e4"]);
+
+    // The engine takes white, the side not on move, and replies to black
+    xboard.send("playother");
+    xboard.send("st 0.05");
+    assert!(xboard.sync().is_empty());
+    xboard.send("e7e5");
+    assert!(xboard.read_line().starts_with("move "));
+}